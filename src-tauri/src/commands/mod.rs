@@ -1,15 +1,22 @@
-use std::{ collections::HashMap, fmt::Display, env };
+use std::{ collections::HashMap, fmt::Display, env, path::PathBuf };
 
 use serde::Serialize;
+use tauri::State;
 use tauri::api::version;
 
+use crate::logging;
+use crate::config::{ self, Config, ClientFlags, Flags };
+use crate::instance::LaunchState;
 use crate::installer::{
     studio,
     download_from_repo,
     player,
+    process::LaunchReport,
+    logs,
+    launch_uri::{ self, PlayerLaunchInfo },
+    lock,
     self,
-    paths::get_downloads_folder,
-    SETUP_URL,
+    paths::{ self, get_downloads_folder },
     download_file,
 };
 
@@ -19,12 +26,15 @@ type Result<T> = std::result::Result<T, String>;
 fn convert_err<T, V: Display>(check: std::result::Result<T, V>) -> Result<T> {
     match check {
         Ok(v) => Ok(v),
-        Err(e) => Err(format!("{}", e)),
+        Err(e) => {
+            tracing::error!(error = %e, "command failed");
+            Err(format!("{}", e))
+        }
     }
 }
 
 #[tauri::command]
-pub async fn get_available_studio() -> Result<HashMap<String, String>> {
+pub async fn get_available_studio() -> Result<HashMap<String, studio::StudioInfo>> {
     convert_err(studio::get_available().await)
 }
 
@@ -34,8 +44,13 @@ pub fn studio_installed(year: &str) -> bool {
 }
 
 #[tauri::command]
-pub async fn install_studio(year: &str, url: &str) -> Result<()> {
-    convert_err(studio::download_studio(year, url).await)
+pub async fn install_studio(year: &str) -> Result<()> {
+    convert_err(studio::download_studio(year).await)
+}
+
+#[tauri::command]
+pub async fn studio_outdated(year: &str) -> Result<bool> {
+    convert_err(studio::is_outdated(year).await)
 }
 
 #[tauri::command]
@@ -50,13 +65,39 @@ pub async fn get_client_manifest(year: &str) -> Result<HashMap<String, String>>
 
 #[tauri::command]
 pub async fn download_zip(file_name: &str) -> Result<()> {
-    let download_url = format!("https://{}/{}", SETUP_URL, file_name);
+    let setup_url = config::get().server().setup_url;
+    let download_url = format!("https://{}/{}", setup_url, file_name);
     let download_folder = convert_err(get_downloads_folder())?;
     let download_path = download_folder.join(file_name);
 
     convert_err(download_file(download_url, download_path).await)
 }
 
+/* Refuses a client install up front when its zips wont fit, check_extract_space covers what they hold */
+#[tauri::command]
+pub async fn check_download_space(file_names: Vec<String>) -> Result<()> {
+    let setup_url = config::get().server().setup_url;
+    let urls: Vec<String> = file_names
+        .iter()
+        .map(|file_name| format!("https://{}/{}", setup_url, file_name))
+        .collect();
+    let download_folder = convert_err(get_downloads_folder())?;
+
+    convert_err(installer::ensure_space_for(&urls, download_folder).await)
+}
+
+/* Run once the zips are downloaded, before any of them is extracted into the client folder */
+#[tauri::command]
+pub fn check_extract_space(file_names: Vec<String>, location: &str) -> Result<()> {
+    let download_folder = convert_err(get_downloads_folder())?;
+    let zips: Vec<PathBuf> = file_names
+        .iter()
+        .map(|file_name| download_folder.join(file_name))
+        .collect();
+
+    convert_err(installer::ensure_extract_space(&zips, location))
+}
+
 #[tauri::command]
 pub async fn extract_zip(file_name: &str, location: &str) -> Result<()> {
     let download_folder = convert_err(get_downloads_folder())?;
@@ -76,10 +117,15 @@ pub async fn prepare_client(
     version: &str,
     manifest: HashMap<String, String>
 ) -> Result<()> {
-    println!("{:?}", manifest);
+    tracing::debug!(year, version, ?manifest, "preparing client");
     convert_err(player::prepare_client(year, version, manifest).await)
 }
 
+#[tauri::command]
+pub fn prune_client_versions(year: &str, version: &str) -> Result<()> {
+    convert_err(player::prune_versions(year, version))
+}
+
 #[tauri::command]
 pub fn client_installed(year: &str, version: &str) -> bool {
     player::installed(year, version)
@@ -106,32 +152,267 @@ pub struct BootstrapperInfo {
 pub fn get_bootstrapper_info() -> BootstrapperInfo {
     BootstrapperInfo {
         compile_time: macros::compile_time!(),
-        base_url: installer::BASE_URL.into(),
+        base_url: config::get().server().base_url,
         pkg_version: env!("CARGO_PKG_VERSION").into(),
     }
 }
 
 #[tauri::command]
-pub async fn create_uri() -> Result<()> {
+pub async fn check_launcher_update() -> Result<Option<installer::self_update::LauncherUpdate>> {
+    convert_err(installer::self_update::check().await)
+}
+
+/* The new launcher is used from the next launch */
+#[tauri::command]
+pub async fn install_launcher_update() -> Result<Option<String>> {
+    convert_err(installer::self_update::install().await)
+}
+
+#[tauri::command]
+pub async fn finish_launcher_update() -> Result<bool> {
+    convert_err(installer::self_update::finish_update().await)
+}
+
+#[tauri::command]
+pub async fn create_uri() -> Result<bool> {
     convert_err(installer::uri::register_uri().await)
 }
 
 #[tauri::command]
-pub async fn create_shortcuts(studio_versions: Vec<&str>) -> Result<()> {
+pub async fn create_place_association(take_over: bool) -> Result<bool> {
+    convert_err(installer::uri::register_place_files(take_over).await)
+}
+
+#[tauri::command]
+pub async fn unregister_uri() -> Result<()> {
+    convert_err(installer::uri::unregister_uri().await)
+}
+
+#[tauri::command]
+pub async fn create_shortcuts(studio_versions: Vec<&str>) -> Result<bool> {
     convert_err(installer::uri::create_studio_shortcuts(studio_versions).await)
 }
 
 #[tauri::command]
-pub async fn launch_studio(year: &str) -> Result<()> {
-    convert_err(studio::launch_studio(year).await)
+pub async fn launch_studio(
+    year: &str,
+    file: Option<&str>,
+    crash_window: Option<u64>
+) -> Result<LaunchReport> {
+    convert_err(studio::launch_studio(year, file, crash_window).await)
+}
+
+/* Source is a url, a plugin file or a folder of them */
+#[tauri::command]
+pub async fn install_studio_plugins(year: &str, source: &str) -> Result<Vec<String>> {
+    convert_err(installer::plugins::install_plugins(year, source).await)
+}
+
+#[tauri::command]
+pub async fn update_studio_plugins(year: &str) -> Result<Vec<String>> {
+    convert_err(installer::plugins::update_plugins(year).await)
+}
+
+#[tauri::command]
+pub fn remove_studio_plugin(year: &str, name: &str) -> Result<()> {
+    convert_err(installer::plugins::remove_plugin(year, name))
+}
+
+#[tauri::command]
+pub fn list_studio_plugins(year: &str) -> Result<Vec<installer::plugins::Plugin>> {
+    convert_err(installer::plugins::list_plugins(year))
+}
+
+/* For places opened from the file manager, which dont say which studio they want */
+#[tauri::command]
+pub fn resolve_place_year(file: &str) -> Result<String> {
+    convert_err(installer::places::resolve_year(file))
+}
+
+#[tauri::command]
+pub fn list_content_packs() -> Result<Vec<String>> {
+    convert_err(installer::content_packs::list_packs())
+}
+
+/* Run after a client install, launches apply them again when the packs changed */
+#[tauri::command]
+pub fn apply_content_packs(year: &str, version: &str) -> Result<bool> {
+    convert_err(installer::content_packs::apply_packs(year, version))
+}
+
+#[tauri::command]
+pub fn revert_content_packs(year: &str, version: &str) -> Result<()> {
+    convert_err(installer::content_packs::revert_packs(year, version))
 }
 
 #[tauri::command]
-pub async fn launch_client(year: &str, version: &str, args: Vec<&str>) -> Result<()> {
-    convert_err(player::launch_client(year, version, &args).await)
+pub fn open_content_packs_folder() -> Result<()> {
+    let folder = convert_err(paths::get_content_packs_folder())?;
+    convert_err(installer::open_path(folder))
+}
+
+/* The flags a launch of that year writes, presets already merged in */
+#[tauri::command]
+pub fn resolve_client_flags(year: &str) -> Flags {
+    installer::client_settings::resolve_flags(year)
+}
+
+#[tauri::command]
+pub fn get_client_flags(year: &str) -> ClientFlags {
+    installer::client_settings::get_client_flags(year)
+}
+
+#[tauri::command]
+pub fn set_client_flags(year: &str, client: ClientFlags) -> Result<()> {
+    convert_err(installer::client_settings::set_client_flags(year, client))
+}
+
+#[tauri::command]
+pub fn set_flag_preset(name: &str, flags: Option<Flags>) -> Result<()> {
+    convert_err(installer::client_settings::set_flag_preset(name, flags))
+}
+
+#[tauri::command]
+pub fn get_player_launch(state: State<'_, LaunchState>) -> Result<PlayerLaunchInfo> {
+    let args = state.current();
+    let launch = convert_err(launch_uri::from_args(&args))?;
+
+    Ok(launch.info())
+}
+
+/* The uri is parsed again here so the auth ticket never has to pass through the ui */
+#[tauri::command]
+pub async fn launch_client(
+    year: &str,
+    version: &str,
+    crash_window: Option<u64>,
+    state: State<'_, LaunchState>
+) -> Result<LaunchReport> {
+    let args = state.current();
+    let launch = convert_err(launch_uri::from_args(&args))?;
+
+    convert_err(player::launch_client(year, version, &launch, crash_window).await)
+}
+
+#[tauri::command]
+pub fn open_launch_log() -> Result<()> {
+    let Some(log) = convert_err(logs::latest_launch_log())? else {
+        return Err("No launch logs yet".into());
+    };
+    convert_err(installer::open_path(log))
+}
+
+/* Defaults to the users downloads folder */
+#[tauri::command]
+pub fn export_launch_log(destination: Option<String>) -> Result<String> {
+    let destination = match destination {
+        Some(path) => PathBuf::from(path),
+        None =>
+            match dirs::download_dir() {
+                Some(path) => path,
+                None => {
+                    return Err("Couldnt find the downloads folder".into());
+                }
+            }
+    };
+
+    let Some(exported) = convert_err(logs::export_latest(destination))? else {
+        return Err("No launch logs yet".into());
+    };
+    Ok(exported.to_string_lossy().into())
+}
+
+#[tauri::command]
+pub fn open_logs_folder() -> Result<()> {
+    let folder = convert_err(paths::get_logs_folder())?;
+    convert_err(installer::open_path(folder))
+}
+
+#[tauri::command]
+pub fn find_broken_installs() -> Result<installer::repair::BrokenInstalls> {
+    convert_err(installer::repair::find_broken())
+}
+
+/* Defaults to the users downloads folder */
+#[tauri::command]
+pub fn bundle_logs(destination: Option<String>) -> Result<String> {
+    let destination = match destination {
+        Some(path) => PathBuf::from(path),
+        None =>
+            match dirs::download_dir() {
+                Some(path) => path,
+                None => {
+                    return Err("Couldnt find the downloads folder".into());
+                }
+            }
+    };
+
+    let bundle = convert_err(logging::bundle_logs(destination))?;
+    Ok(bundle.to_string_lossy().into())
+}
+
+/* Secrets are masked, get_player_launch is what reads the player uri */
+#[tauri::command]
+pub fn get_cli(state: State<'_, LaunchState>) -> Vec<String> {
+    logging::redact_args(&state.current())
+}
+
+/* Moves on to a launch forwarded by another launcher, false when the ui can exit */
+#[tauri::command]
+pub fn next_launch(state: State<'_, LaunchState>, closing: bool) -> bool {
+    state.advance(closing)
+}
+
+/* Client installs are driven from the ui so the folder lock is held across commands */
+#[tauri::command]
+pub fn lock_client_install(year: &str, version: &str) -> Result<()> {
+    let folder = convert_err(player::get_client_folder(year, version))?;
+    convert_err(lock::hold(folder))
+}
+
+#[tauri::command]
+pub fn unlock_client_install(year: &str, version: &str) -> Result<()> {
+    let folder = convert_err(player::get_client_folder(year, version))?;
+    convert_err(lock::release(folder))
+}
+
+#[tauri::command]
+pub fn uninstall_client(year: &str, version: Option<&str>) -> Result<()> {
+    convert_err(installer::uninstall::uninstall_client(year, version))
+}
+
+#[tauri::command]
+pub async fn uninstall_studio(year: &str) -> Result<()> {
+    convert_err(installer::uninstall::uninstall_studio(year).await)
+}
+
+#[tauri::command]
+pub async fn uninstall_all() -> Result<()> {
+    convert_err(installer::uninstall::uninstall_all().await)
+}
+
+#[tauri::command]
+pub fn get_config() -> Config {
+    config::get()
+}
+
+/* Moving installs has to go through migrate_install so nothing gets left behind */
+#[tauri::command]
+pub fn set_config(config: Config) -> Result<()> {
+    if config.install_root != config::get().install_root {
+        if paths::portable_root().is_some() {
+            return Err("Portable installs always live beside the launcher, install_root cant be set".into());
+        }
+        return Err("Use migrate_install to change the install location".into());
+    }
+    convert_err(config::set(config))
 }
 
+/* Copying installs can take minutes so it runs on a blocking thread, the error is a string since it has to be Send */
 #[tauri::command]
-pub fn get_cli() -> Vec<String> {
-    env::args().collect()
+pub async fn migrate_install(new_root: String) -> Result<()> {
+    let migrated = tauri::async_runtime::spawn_blocking(move || {
+        installer::migrate::migrate(new_root).map_err(|e| e.to_string())
+    });
+    convert_err(convert_err(migrated.await)?)
 }
@@ -0,0 +1,449 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{ self, Display },
+    fs,
+    path::PathBuf,
+    sync::{ Mutex, RwLock },
+};
+
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+
+use crate::installer::{ paths, APP_NAME, BASE_URL, SETUP_URL, REPO_NAME, TARGET_BRANCH };
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const CONFIG_FILE: &str = "config.json";
+const DEFAULT_PROFILE: &str = "rbtest";
+const LOG_LEVELS: [&str; 5] = ["trace", "debug", "info", "warn", "error"];
+const MAX_CONCURRENCY: usize = 32;
+
+#[derive(Debug)]
+pub struct InvalidConfig {
+    reason: String,
+}
+
+impl Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config: {}", self.reason)
+    }
+}
+
+impl Error for InvalidConfig {}
+
+fn invalid<T, R: Into<String>>(reason: R) -> Result<T> {
+    Err(InvalidConfig { reason: reason.into() }.into())
+}
+
+/* Where clients.json, studios.json and the manifests are read from */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataSource {
+    pub repo: String,
+    pub branch: String,
+}
+
+impl Default for MetadataSource {
+    fn default() -> Self {
+        MetadataSource {
+            repo: REPO_NAME.into(),
+            branch: TARGET_BRANCH.into(),
+        }
+    }
+}
+
+/* Hosts are stored without a scheme, same as the old constants */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerProfile {
+    pub base_url: String,
+    pub setup_url: String,
+}
+
+impl Default for ServerProfile {
+    fn default() -> Self {
+        ServerProfile {
+            base_url: BASE_URL.into(),
+            setup_url: SETUP_URL.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Retention {
+    /* Client versions kept per year, the one being launched is never removed */
+    pub client_versions: usize,
+    /* Studio builds kept per year, older ones are only removed after an update was verified */
+    pub studio_versions: usize,
+    pub launch_logs: usize,
+    /* One launcher log is written per day */
+    pub launcher_logs: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            client_versions: 2,
+            studio_versions: 2,
+            launch_logs: 10,
+            launcher_logs: 7,
+        }
+    }
+}
+
+/* Only used on linux */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WineRunner {
+    pub binary: String,
+    /* Placed before the exe, eg for runners that need a subcommand */
+    pub args: Vec<String>,
+    pub prefix: Option<PathBuf>,
+}
+
+impl Default for WineRunner {
+    fn default() -> Self {
+        WineRunner {
+            binary: "wine".into(),
+            args: vec![],
+            prefix: None,
+        }
+    }
+}
+
+/* FFlag values are bools, numbers or strings, same as ClientAppSettings.json takes them */
+pub type Flags = BTreeMap<String, serde_json::Value>;
+
+/* Presets are applied in order, flags set here win over all of them */
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientFlags {
+    pub presets: Vec<String>,
+    pub flags: Flags,
+}
+
+fn default_flag_presets() -> BTreeMap<String, Flags> {
+    let mut presets = BTreeMap::new();
+    presets.insert(
+        "uncapped-fps".to_string(),
+        Flags::from([("DFIntTaskSchedulerTargetFps".to_string(), serde_json::json!(9999))])
+    );
+    presets.insert(
+        "opengl".to_string(),
+        Flags::from([("FFlagDebugGraphicsPreferOpenGL".to_string(), serde_json::json!(true))])
+    );
+    presets.insert(
+        "vulkan".to_string(),
+        Flags::from([("FFlagDebugGraphicsPreferVulkan".to_string(), serde_json::json!(true))])
+    );
+    presets
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /* None keeps everything in the local data folder */
+    pub install_root: Option<PathBuf>,
+    pub metadata: MetadataSource,
+    pub server_profile: String,
+    pub servers: BTreeMap<String, ServerProfile>,
+    /* Downloads allowed to run at the same time */
+    pub concurrency: usize,
+    /* Bytes per second shared by all downloads */
+    pub bandwidth_limit: Option<u64>,
+    pub retention: Retention,
+    pub wine: WineRunner,
+    /* Studio year for place files that were never opened before */
+    pub default_studio_year: Option<String>,
+    pub flag_presets: BTreeMap<String, Flags>,
+    /* Keyed by client year, written to ClientAppSettings.json on every launch */
+    pub client_flags: BTreeMap<String, ClientFlags>,
+    pub log_level: String,
+    /* Installs signed launcher updates when a launch starts */
+    pub auto_update: bool,
+    pub close_after_launch: bool,
+    pub close_delay_ms: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut servers = BTreeMap::new();
+        servers.insert(DEFAULT_PROFILE.to_string(), ServerProfile::default());
+
+        Config {
+            install_root: None,
+            metadata: MetadataSource::default(),
+            server_profile: DEFAULT_PROFILE.into(),
+            servers,
+            concurrency: 4,
+            bandwidth_limit: None,
+            retention: Retention::default(),
+            wine: WineRunner::default(),
+            default_studio_year: None,
+            flag_presets: default_flag_presets(),
+            client_flags: BTreeMap::new(),
+            log_level: "info".into(),
+            auto_update: true,
+            close_after_launch: true,
+            close_delay_ms: 3000,
+        }
+    }
+}
+
+fn validate_year(name: &str, year: &str) -> Result<()> {
+    if year.len() != 4 || !year.chars().all(|c| c.is_ascii_digit()) {
+        return invalid(format!("{} must be a year like 2016", name));
+    }
+    Ok(())
+}
+
+fn validate_flags(name: &str, flags: &Flags) -> Result<()> {
+    for (flag, value) in flags {
+        if flag.is_empty() || !flag.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return invalid(format!("{} has an invalid flag name {:?}", name, flag));
+        }
+        if !(value.is_boolean() || value.is_number() || value.is_string()) {
+            return invalid(format!("{}.{} must be a bool, number or string", name, flag));
+        }
+    }
+    Ok(())
+}
+
+fn validate_host(name: &str, host: &str) -> Result<()> {
+    if host.is_empty() || host.contains("://") || host.contains('/') || host.contains(' ') {
+        return invalid(format!("{} must be a bare host name, got {:?}", name, host));
+    }
+    Ok(())
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<()> {
+        if let Some(root) = &self.install_root {
+            if !root.is_absolute() {
+                return invalid("install_root must be an absolute path");
+            }
+        }
+
+        let mut repo = self.metadata.repo.split('/');
+        let valid_repo = matches!(
+            (repo.next(), repo.next(), repo.next()),
+            (Some(owner), Some(name), None) if !owner.is_empty() && !name.is_empty()
+        );
+        if !valid_repo {
+            return invalid("metadata.repo must look like owner/name");
+        }
+        if self.metadata.branch.is_empty() {
+            return invalid("metadata.branch cant be empty");
+        }
+
+        if !self.servers.contains_key(&self.server_profile) {
+            return invalid(format!("server profile {} does not exist", self.server_profile));
+        }
+        for (name, profile) in &self.servers {
+            validate_host(&format!("servers.{}.base_url", name), &profile.base_url)?;
+            validate_host(&format!("servers.{}.setup_url", name), &profile.setup_url)?;
+        }
+
+        if self.concurrency == 0 || self.concurrency > MAX_CONCURRENCY {
+            return invalid(format!("concurrency must be between 1 and {}", MAX_CONCURRENCY));
+        }
+        if self.bandwidth_limit == Some(0) {
+            return invalid("bandwidth_limit must be above 0, leave it out for no limit");
+        }
+
+        let retention = &self.retention;
+        let counts = [retention.client_versions, retention.studio_versions, retention.launch_logs, retention.launcher_logs];
+        if counts.contains(&0) {
+            return invalid("retention values must be at least 1");
+        }
+
+        if self.wine.binary.is_empty() {
+            return invalid("wine.binary cant be empty");
+        }
+        if let Some(year) = &self.default_studio_year {
+            validate_year("default_studio_year", year)?;
+        }
+        for (name, flags) in &self.flag_presets {
+            if name.is_empty() {
+                return invalid("flag preset names cant be empty");
+            }
+            validate_flags(&format!("flag_presets.{}", name), flags)?;
+        }
+        for (year, client) in &self.client_flags {
+            let name = format!("client_flags.{}", year);
+            validate_year(&name, year)?;
+            validate_flags(&name, &client.flags)?;
+            if let Some(missing) = client.presets.iter().find(|preset| !self.flag_presets.contains_key(*preset)) {
+                return invalid(format!("{} uses preset {} which does not exist", name, missing));
+            }
+        }
+        if !LOG_LEVELS.contains(&self.log_level.as_str()) {
+            return invalid(format!("log_level must be one of {}", LOG_LEVELS.join(", ")));
+        }
+
+        Ok(())
+    }
+
+    /* Validation makes sure the active profile exists */
+    pub fn server(&self) -> ServerProfile {
+        self.servers.get(&self.server_profile).cloned().unwrap_or_default()
+    }
+}
+
+/*
+    Lives outside the install root so moving installs never moves the config.
+    Portable mode keeps it with everything else beside the binary
+*/
+pub fn config_folder() -> Result<PathBuf> {
+    if let Some(root) = paths::portable_root() {
+        fs::create_dir_all(&root)?;
+        return Ok(root);
+    }
+
+    let Some(dir) = dirs::config_dir() else {
+        return invalid("No config folder on this system");
+    };
+    let dir = dir.join(APP_NAME);
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir)
+}
+
+pub fn config_path() -> Result<PathBuf> {
+    Ok(config_folder()?.join(CONFIG_FILE))
+}
+
+/* Everything in read that did not make it into known, as dotted paths */
+fn unknown_keys(prefix: &str, read: &Value, known: &Value, found: &mut Vec<String>) {
+    let (Value::Object(read), Value::Object(known)) = (read, known) else {
+        return;
+    };
+
+    for (key, value) in read {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match known.get(key) {
+            Some(known) => unknown_keys(&path, value, known, found),
+            None => found.push(path),
+        }
+    }
+}
+
+fn load() -> Result<Config> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    /* Keys from a newer launcher or a typo are skipped rather than failing the whole file */
+    let read: Value = serde_json::from_slice(&fs::read(path)?)?;
+    let config: Config = serde_json::from_value(read.clone())?;
+    let mut unknown = vec![];
+    unknown_keys("", &read, &serde_json::to_value(&config)?, &mut unknown);
+    if !unknown.is_empty() {
+        note(LoadIssue::UnknownKeys(unknown));
+    }
+    config.validate()?;
+
+    Ok(config)
+}
+
+static CONFIG: RwLock<Option<Config>> = RwLock::new(None);
+
+/* The config is read to set up logging, so anything wrong with it is held until logging can report it */
+enum LoadIssue {
+    UnknownKeys(Vec<String>),
+    Failed(String),
+}
+
+static LOAD_ISSUES: Mutex<Vec<LoadIssue>> = Mutex::new(Vec::new());
+
+fn note(issue: LoadIssue) {
+    if let Ok(mut issues) = LOAD_ISSUES.lock() {
+        issues.push(issue);
+    }
+}
+
+/* Called by logging::init once the subscriber is installed */
+pub fn report_load_issues() {
+    let issues = LOAD_ISSUES.lock().map(|mut issues| std::mem::take(&mut *issues)).unwrap_or_default();
+    for issue in issues {
+        match issue {
+            LoadIssue::UnknownKeys(keys) => tracing::warn!(keys = ?keys, "ignoring unknown config keys"),
+            LoadIssue::Failed(error) => tracing::warn!(error, "failed to load config, using defaults"),
+        }
+    }
+}
+
+/* A broken config file falls back to the defaults instead of stopping the launcher */
+pub fn get() -> Config {
+    if let Some(config) = CONFIG.read().ok().and_then(|config| config.clone()) {
+        return config;
+    }
+
+    let config = load().unwrap_or_else(|e| {
+        note(LoadIssue::Failed(e.to_string()));
+        Config::default()
+    });
+
+    if let Ok(mut cached) = CONFIG.write() {
+        *cached = Some(config.clone());
+    }
+    config
+}
+
+pub fn set(config: Config) -> Result<()> {
+    config.validate()?;
+
+    /* Write then rename so a crash never leaves half a config behind */
+    let path = config_path()?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, serde_json::to_vec_pretty(&config)?)?;
+    fs::rename(temp, path)?;
+
+    if let Ok(mut cached) = CONFIG.write() {
+        *cached = Some(config);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unknown(json: Value) -> Vec<String> {
+        let config: Config = serde_json::from_value(json.clone()).unwrap();
+        let mut found = vec![];
+        unknown_keys("", &json, &serde_json::to_value(&config).unwrap(), &mut found);
+        found
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let json = serde_json::json!({
+            "concurrency": 8,
+            "theme": "dark",
+            "retention": { "client_versions": 3, "studio_version": 1 },
+            "servers": { "rbtest": { "base_url": "a.com", "setup_url": "setup.a.com", "port": 80 } }
+        });
+
+        let config: Config = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(config.concurrency, 8);
+        assert_eq!(config.retention.client_versions, 3);
+        assert_eq!(config.server().base_url, "a.com");
+        assert_eq!(unknown(json), ["retention.studio_version", "servers.rbtest.port", "theme"]);
+    }
+
+    #[test]
+    fn known_keys_are_not_reported() {
+        let json = serde_json::to_value(Config::default()).unwrap();
+        assert!(unknown(json).is_empty());
+
+        let json = serde_json::json!({
+            "install_root": null,
+            "client_flags": { "2016": { "flags": { "FFlagTest": true } } },
+            "wine": { "prefix": "/tmp/prefix" }
+        });
+        assert!(unknown(json).is_empty());
+    }
+}
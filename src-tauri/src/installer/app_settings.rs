@@ -0,0 +1,339 @@
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{ Component, Path, PathBuf };
+
+use xml::common::XmlVersion;
+use xml::reader::{ EventReader, ParserConfig, XmlEvent as ReaderEvent };
+use xml::writer::{ EmitterConfig, XmlEvent as WriterEvent };
+
+use super::Result;
+use crate::config;
+
+/*
+    Every client and studio year reads the same AppSettings.xml, a Settings element holding
+    plain text children. The two we know are modeled, anything else is carried along untouched
+*/
+
+pub const APP_SETTINGS_FILE: &str = "AppSettings.xml";
+
+const ROOT_ELEMENT: &str = "Settings";
+const CONTENT_FOLDER: &str = "ContentFolder";
+const BASE_URL: &str = "BaseUrl";
+
+const DEFAULT_CONTENT_FOLDER: &str = "content";
+
+/* Used when the file didnt have a base url to copy the scheme from */
+const DEFAULT_SCHEME: &str = "http://";
+
+#[derive(Debug)]
+pub enum AppSettingsError {
+    WrongRoot(String),
+    Nested(String),
+}
+
+impl Display for AppSettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppSettingsError::WrongRoot(name) => write!(f, "AppSettings root is {} instead of {}", name, ROOT_ELEMENT),
+            AppSettingsError::Nested(name) => write!(f, "AppSettings element {} has children", name),
+        }
+    }
+}
+
+impl Error for AppSettingsError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppSettings {
+    pub content_folder: String,
+    /* Includes the scheme, eg http://www.rbtest.org */
+    pub base_url: String,
+    /* Elements we dont model, kept in order so a rewrite never drops them */
+    extra: Vec<(String, String)>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            content_folder: DEFAULT_CONTENT_FOLDER.into(),
+            base_url: String::new(),
+            extra: vec![],
+        }
+    }
+}
+
+impl AppSettings {
+    /* Missing known elements keep their defaults, xml-rs already refuses unclosed documents */
+    pub fn parse(content: &str) -> Result<Self> {
+        let config = ParserConfig::new().trim_whitespace(true).ignore_comments(true);
+        let mut settings = AppSettings::default();
+        let mut depth = 0;
+        let mut current: Option<(String, String)> = None;
+
+        for event in EventReader::new_with_config(content.as_bytes(), config) {
+            match event? {
+                ReaderEvent::StartElement { name, .. } => {
+                    depth += 1;
+                    match depth {
+                        1 if name.local_name != ROOT_ELEMENT => {
+                            return Err(AppSettingsError::WrongRoot(name.local_name).into());
+                        }
+                        1 => {}
+                        2 => {
+                            current = Some((name.local_name, String::new()));
+                        }
+                        _ => {
+                            let parent = current.map(|(parent, _)| parent).unwrap_or_default();
+                            return Err(AppSettingsError::Nested(parent).into());
+                        }
+                    }
+                }
+                ReaderEvent::Characters(text) | ReaderEvent::CData(text) => {
+                    if let Some((_, value)) = current.as_mut() {
+                        value.push_str(&text);
+                    }
+                }
+                ReaderEvent::EndElement { .. } => {
+                    depth -= 1;
+                    if let Some((name, value)) = current.take() {
+                        settings.set(name, value);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(settings)
+    }
+
+    fn set(&mut self, name: String, value: String) {
+        match name.as_str() {
+            CONTENT_FOLDER => {
+                self.content_folder = value;
+            }
+            BASE_URL => {
+                self.base_url = value;
+            }
+            _ =>
+                match self.extra.iter_mut().find(|(existing, _)| *existing == name) {
+                    Some((_, existing)) => {
+                        *existing = value;
+                    }
+                    None => self.extra.push((name, value)),
+                }
+        }
+    }
+
+    fn elements(&self) -> Vec<(&str, &str)> {
+        let mut elements = vec![(CONTENT_FOLDER, self.content_folder.as_str()), (BASE_URL, self.base_url.as_str())];
+        elements.extend(self.extra.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+        elements
+    }
+
+    pub fn to_xml(&self) -> Result<String> {
+        let mut out = vec![];
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .indent_string("\t")
+            .create_writer(&mut out);
+
+        writer.write(WriterEvent::StartDocument {
+            version: XmlVersion::Version10,
+            encoding: Some("UTF-8"),
+            standalone: None,
+        })?;
+        writer.write(WriterEvent::start_element(ROOT_ELEMENT))?;
+        for (name, value) in self.elements() {
+            writer.write(WriterEvent::start_element(name))?;
+            writer.write(WriterEvent::characters(value))?;
+            writer.write(WriterEvent::end_element())?;
+        }
+        writer.write(WriterEvent::end_element())?;
+
+        let mut xml = String::from_utf8(out)?;
+        xml.push('\n');
+        Ok(xml)
+    }
+
+    /* Keeps the scheme and trailing slash the file already used since some years join paths onto it */
+    pub fn set_host(&mut self, host: &str) {
+        let (scheme, rest) = match self.base_url.find("://") {
+            Some(index) => self.base_url.split_at(index + 3),
+            None => (DEFAULT_SCHEME, self.base_url.as_str()),
+        };
+        let slash = if rest.ends_with('/') { "/" } else { "" };
+        self.base_url = format!("{}{}{}", scheme, host, slash);
+    }
+}
+
+/*
+    Points the install at the active server profile, writing the file when the zip didnt ship one.
+    A file that doesnt parse is replaced. Returns true when anything was written
+*/
+pub fn apply<P: AsRef<Path>>(install_folder: P) -> Result<bool> {
+    let path = install_folder.as_ref().join(APP_SETTINGS_FILE);
+    let existing = match fs::read_to_string(&path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(e.into());
+        }
+    };
+
+    let mut settings = match existing.as_deref().map(AppSettings::parse) {
+        Some(Ok(settings)) => settings,
+        Some(Err(e)) => {
+            tracing::warn!(file = %path.display(), error = %e, "replacing unreadable app settings");
+            AppSettings::default()
+        }
+        None => AppSettings::default(),
+    };
+    settings.set_host(&config::get().server().base_url);
+
+    let xml = settings.to_xml()?;
+    if existing.is_some_and(|existing| existing == xml) {
+        return Ok(false);
+    }
+
+    tracing::info!(file = %path.display(), base_url = settings.base_url, "writing app settings");
+    let temp = path.with_extension("xml.tmp");
+    fs::write(&temp, xml)?;
+    fs::rename(temp, path)?;
+    Ok(true)
+}
+
+/* Falls back to the default when the file is unreadable or points outside the install */
+pub fn content_folder<P: AsRef<Path>>(install_folder: P) -> PathBuf {
+    let install_folder = install_folder.as_ref();
+    let configured = fs
+        ::read_to_string(install_folder.join(APP_SETTINGS_FILE))
+        .ok()
+        .and_then(|content| AppSettings::parse(&content).ok())
+        .map(|settings| PathBuf::from(settings.content_folder))
+        .filter(|folder| {
+            folder.components().next().is_some() &&
+                folder.components().all(|component| matches!(component, Component::Normal(_)))
+        });
+
+    install_folder.join(configured.unwrap_or_else(|| PathBuf::from(DEFAULT_CONTENT_FOLDER)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* What each year shipped with, and what the launcher writes over it for the test host */
+    macro_rules! fixture {
+        ($name:literal) => {
+            (
+                $name,
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/app_settings/", $name, ".xml")),
+                include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/app_settings/", $name, ".applied.xml")),
+            )
+        };
+    }
+
+    const FIXTURES: [(&str, &str, &str); 10] = [
+        fixture!("client-2014"),
+        fixture!("client-2016"),
+        fixture!("client-2018"),
+        fixture!("client-2020"),
+        fixture!("client-2021"),
+        fixture!("studio-2014"),
+        fixture!("studio-2016"),
+        fixture!("studio-2018"),
+        fixture!("studio-2020"),
+        fixture!("studio-2021"),
+    ];
+
+    const TEST_HOST: &str = "www.example.org";
+
+    #[test]
+    fn fixtures_parse() {
+        for (name, shipped, _) in FIXTURES {
+            let settings = AppSettings::parse(shipped).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(settings.content_folder, "content", "{}", name);
+            assert!(settings.base_url.contains("://www.syntax.eco"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for (name, shipped, _) in FIXTURES {
+            let settings = AppSettings::parse(shipped).unwrap();
+            let written = settings.to_xml().unwrap();
+            let reparsed = AppSettings::parse(&written).unwrap();
+            assert_eq!(reparsed, settings, "{}", name);
+            assert_eq!(reparsed.to_xml().unwrap(), written, "{}", name);
+        }
+    }
+
+    #[test]
+    fn extras_are_kept() {
+        let (_, shipped, _) = fixture!("studio-2020");
+        let settings = AppSettings::parse(shipped).unwrap();
+        assert_eq!(settings.extra, [
+            ("PluginsFolder".to_string(), "BuiltInPlugins".to_string()),
+            ("QtWebEngine".to_string(), "--disable-gpu".to_string()),
+        ]);
+
+        let (_, shipped, _) = fixture!("client-2021");
+        let mut settings = AppSettings::parse(shipped).unwrap();
+        settings.set_host(TEST_HOST);
+        let reparsed = AppSettings::parse(&settings.to_xml().unwrap()).unwrap();
+        assert_eq!(reparsed.extra, [("ClientAppSettingsPath".to_string(), "ClientSettings".to_string())]);
+    }
+
+    #[test]
+    fn set_host_keeps_scheme_and_slash() {
+        for (name, shipped, _) in FIXTURES {
+            let original = AppSettings::parse(shipped).unwrap();
+            let mut settings = original.clone();
+            settings.set_host(TEST_HOST);
+
+            let scheme = &original.base_url[..original.base_url.find("://").unwrap() + 3];
+            let slash = if original.base_url.ends_with('/') { "/" } else { "" };
+            assert_eq!(settings.base_url, format!("{}{}{}", scheme, TEST_HOST, slash), "{}", name);
+        }
+    }
+
+    #[test]
+    fn set_host_without_base_url() {
+        let mut settings = AppSettings::parse("<Settings><ContentFolder>content</ContentFolder></Settings>").unwrap();
+        settings.set_host(TEST_HOST);
+        assert_eq!(settings.base_url, format!("{}{}", DEFAULT_SCHEME, TEST_HOST));
+    }
+
+    #[test]
+    fn golden_output() {
+        for (name, shipped, applied) in FIXTURES {
+            let mut settings = AppSettings::parse(shipped).unwrap();
+            settings.set_host(TEST_HOST);
+            assert_eq!(settings.to_xml().unwrap(), applied, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(AppSettings::parse("<Other><BaseUrl>x</BaseUrl></Other>").is_err());
+        assert!(AppSettings::parse("<Settings><BaseUrl><a/></BaseUrl></Settings>").is_err());
+        assert!(AppSettings::parse("<Settings><BaseUrl>x</BaseUrl>").is_err());
+    }
+
+    #[test]
+    fn content_folder_stays_inside_the_install() {
+        let install = tempfile::tempdir().unwrap();
+        let write = |folder: &str| {
+            let settings = AppSettings { content_folder: folder.into(), ..AppSettings::default() };
+            fs::write(install.path().join(APP_SETTINGS_FILE), settings.to_xml().unwrap()).unwrap();
+        };
+
+        write("data/content");
+        assert_eq!(content_folder(install.path()), install.path().join("data/content"));
+        write("../content");
+        assert_eq!(content_folder(install.path()), install.path().join(DEFAULT_CONTENT_FOLDER));
+        write("/etc");
+        assert_eq!(content_folder(install.path()), install.path().join(DEFAULT_CONTENT_FOLDER));
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use super::Result;
+use crate::config::{ self, ClientFlags, Flags };
+
+/*
+    FFlag overrides from the config are written over whatever the client shipped with on every launch,
+    so an update replacing the client folder never loses them
+*/
+
+const CLIENT_SETTINGS_FOLDER: &str = "ClientSettings";
+const CLIENT_SETTINGS_FILE: &str = "ClientAppSettings.json";
+
+/* What the client shipped with, saved the first time it gets overwritten. An empty object when it had none */
+const SHIPPED_SETTINGS_FILE: &str = "ClientAppSettings.shipped.json";
+
+/* Presets in the order they were listed, then the years own flags on top */
+pub fn resolve_flags<T: AsRef<str>>(year: T) -> Flags {
+    let config = config::get();
+    let Some(client) = config.client_flags.get(year.as_ref()) else {
+        return Flags::new();
+    };
+
+    let mut flags = Flags::new();
+    for preset in &client.presets {
+        if let Some(preset) = config.flag_presets.get(preset) {
+            flags.extend(preset.clone());
+        }
+    }
+    flags.extend(client.flags.clone());
+    flags
+}
+
+fn read_shipped(settings_folder: &Path) -> Result<Flags> {
+    let shipped_path = settings_folder.join(SHIPPED_SETTINGS_FILE);
+    match fs::read(&shipped_path) {
+        Ok(bytes) => {
+            return Ok(serde_json::from_slice(&bytes)?);
+        }
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            return Err(e.into());
+        }
+        Err(_) => {}
+    }
+
+    let shipped = match fs::read(settings_folder.join(CLIENT_SETTINGS_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => Flags::new(),
+        Err(e) => {
+            return Err(e.into());
+        }
+    };
+
+    fs::create_dir_all(settings_folder)?;
+    fs::write(shipped_path, serde_json::to_vec_pretty(&shipped)?)?;
+    Ok(shipped)
+}
+
+/* Returns true when the file had to be rewritten */
+pub fn apply_flags<T: AsRef<str>, P: AsRef<Path>>(year: T, client_folder: P) -> Result<bool> {
+    let year = year.as_ref();
+    let settings_folder = client_folder.as_ref().join(CLIENT_SETTINGS_FOLDER);
+
+    let overrides = resolve_flags(year);
+    if overrides.is_empty() && !settings_folder.join(SHIPPED_SETTINGS_FILE).exists() {
+        return Ok(false);
+    }
+
+    let mut flags = read_shipped(&settings_folder)?;
+    flags.extend(overrides);
+
+    let encoded = serde_json::to_vec_pretty(&flags)?;
+    let settings_path = settings_folder.join(CLIENT_SETTINGS_FILE);
+    if fs::read(&settings_path).is_ok_and(|existing| existing == encoded) {
+        return Ok(false);
+    }
+
+    tracing::info!(year, count = flags.len(), "writing client settings");
+    let temp = settings_path.with_extension("json.tmp");
+    fs::write(&temp, encoded)?;
+    fs::rename(temp, settings_path)?;
+    Ok(true)
+}
+
+pub fn get_client_flags<T: AsRef<str>>(year: T) -> ClientFlags {
+    config::get().client_flags.get(year.as_ref()).cloned().unwrap_or_default()
+}
+
+/* An empty set removes the year, the next launch puts back what the client shipped with */
+pub fn set_client_flags<T: AsRef<str>>(year: T, client: ClientFlags) -> Result<()> {
+    let mut config = config::get();
+    if client.presets.is_empty() && client.flags.is_empty() {
+        config.client_flags.remove(year.as_ref());
+    } else {
+        config.client_flags.insert(year.as_ref().to_string(), client);
+    }
+    config::set(config)
+}
+
+/* None deletes the preset, which fails while a year still uses it */
+pub fn set_flag_preset(name: &str, flags: Option<Flags>) -> Result<()> {
+    let mut config = config::get();
+    match flags {
+        Some(flags) => {
+            config.flag_presets.insert(name.to_string(), flags);
+        }
+        None => {
+            config.flag_presets.remove(name);
+        }
+    }
+    config::set(config)
+}
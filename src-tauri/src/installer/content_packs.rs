@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{ Path, PathBuf };
+use std::time::UNIX_EPOCH;
+
+use serde::{ Deserialize, Serialize };
+
+use super::{ app_settings, paths, player, sha256_file, Result };
+
+/*
+    Packs are applied over the content folder of a client version.
+    Files they replace are moved into a backup folder first so reverting puts the version back as it shipped
+*/
+
+/* Written into the version folder, lists every file the packs put into content */
+const APPLIED_FILE: &str = "content-packs.json";
+
+/* Beside content rather than inside it so the client never loads the originals */
+const BACKUP_FOLDER: &str = "content-backup";
+
+/* Size and modified time, a file whose stamp didnt change is taken to still have the recorded hash */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct FileStamp {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(FileStamp { size: metadata.len(), modified_secs: modified.as_secs(), modified_nanos: modified.subsec_nanos() })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AppliedFile {
+    pack: String,
+    sha256: String,
+    /* False when the pack added a file the version didnt have */
+    backed_up: bool,
+    /* Missing in records from before they were kept, which just means hashing once more */
+    #[serde(default)]
+    source: Option<FileStamp>,
+    /* Only set once the copy finished */
+    #[serde(default)]
+    target: Option<FileStamp>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AppliedPacks {
+    packs: Vec<String>,
+    /* Keyed by the path inside content, always with forward slashes */
+    files: BTreeMap<String, AppliedFile>,
+}
+
+/* A file of a pack, the last pack to have a path wins */
+struct PackFile {
+    pack: String,
+    source: PathBuf,
+    sha256: String,
+    stamp: Option<FileStamp>,
+}
+
+fn packs_in(packs_folder: &Path) -> Result<Vec<String>> {
+    let mut packs = vec![];
+    for entry in fs::read_dir(packs_folder)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            packs.push(name.to_string());
+        }
+    }
+
+    packs.sort();
+    Ok(packs)
+}
+
+/* Folders in the packs folder sorted by name, so a number in front sets the order */
+pub fn list_packs() -> Result<Vec<String>> {
+    packs_in(&paths::get_content_packs_folder()?)
+}
+
+/* Links are skipped so a pack cant reach outside its own folder */
+fn collect_files(root: &Path, folder: &Path, files: &mut Vec<(String, PathBuf)>) -> Result<()> {
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative = path
+                .strip_prefix(root)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((relative, path));
+        }
+    }
+
+    Ok(())
+}
+
+/* Pack files are only hashed when they changed since they were applied, packs can be large */
+fn desired_files(packs_folder: &Path, packs: &[String], applied: &AppliedPacks) -> Result<BTreeMap<String, PackFile>> {
+    let mut desired = BTreeMap::new();
+    for pack in packs {
+        let root = packs_folder.join(pack);
+        let mut files = vec![];
+        collect_files(&root, &root, &mut files)?;
+
+        for (relative, source) in files {
+            let stamp = FileStamp::of(&source);
+            let cached = applied.files
+                .get(&relative)
+                .filter(|file| file.pack == *pack && stamp.is_some() && file.source == stamp)
+                .map(|file| file.sha256.clone());
+            let sha256 = match cached {
+                Some(sha256) => sha256,
+                None => sha256_file(&source)?,
+            };
+            desired.insert(relative, PackFile { pack: pack.clone(), source, sha256, stamp });
+        }
+    }
+
+    Ok(desired)
+}
+
+fn read_applied(version_folder: &Path) -> Result<AppliedPacks> {
+    match fs::read(version_folder.join(APPLIED_FILE)) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(AppliedPacks::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/* Written after every file so a crash halfway still leaves a record revert can work from */
+fn write_applied(version_folder: &Path, applied: &AppliedPacks) -> Result<()> {
+    let path = version_folder.join(APPLIED_FILE);
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, serde_json::to_vec_pretty(applied)?)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+fn remove_file_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)?;
+    Ok(())
+}
+
+/* Applied files that were changed by hand since are still replaced, the backup is what came with the version */
+fn revert_folder(version_folder: &Path) -> Result<()> {
+    let mut applied = read_applied(version_folder)?;
+    if applied.files.is_empty() && !version_folder.join(APPLIED_FILE).exists() {
+        return Ok(());
+    }
+
+    let content = app_settings::content_folder(version_folder);
+    let backup = version_folder.join(BACKUP_FOLDER);
+    tracing::info!(folder = %version_folder.display(), files = applied.files.len(), "reverting content packs");
+
+    while let Some((relative, file)) = applied.files.pop_first() {
+        let target = content.join(&relative);
+        let original = backup.join(&relative);
+
+        /* A missing backup means applying stopped before the original was moved, so it is still in place */
+        if !file.backed_up {
+            remove_file_if_exists(&target)?;
+        } else if original.exists() {
+            remove_file_if_exists(&target)?;
+            move_file(&original, &target)?;
+        }
+        write_applied(version_folder, &applied)?;
+    }
+
+    match fs::remove_dir_all(&backup) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e.into());
+        }
+    }
+    remove_file_if_exists(&version_folder.join(APPLIED_FILE))
+}
+
+/*
+    True when every file is still what the packs put there, so nothing has to be copied again.
+    Content files are only hashed when their stamp moved since the copy
+*/
+fn is_current(content: &Path, applied: &AppliedPacks, packs: &[String], desired: &BTreeMap<String, PackFile>) -> bool {
+    applied.packs == packs &&
+        applied.files.len() == desired.len() &&
+        desired.iter().all(|(relative, wanted)| {
+            let Some(file) = applied.files.get(relative) else {
+                return false;
+            };
+            if file.pack != wanted.pack || file.sha256 != wanted.sha256 {
+                return false;
+            }
+
+            let target = content.join(relative);
+            let stamp = FileStamp::of(&target);
+            (stamp.is_some() && file.target == stamp) ||
+                sha256_file(target).is_ok_and(|sha256| sha256 == wanted.sha256)
+        })
+}
+
+/*
+    Puts every pack over the versions content, reverting whatever was applied before when the packs changed.
+    Returns true when the content folder changed
+*/
+pub fn apply_to_folder(version_folder: &Path) -> Result<bool> {
+    apply_from(&paths::get_content_packs_folder()?, version_folder)
+}
+
+fn apply_from(packs_folder: &Path, version_folder: &Path) -> Result<bool> {
+    let packs = packs_in(packs_folder)?;
+    let content = app_settings::content_folder(version_folder);
+
+    let applied = read_applied(version_folder)?;
+    let desired = desired_files(packs_folder, &packs, &applied)?;
+    if is_current(&content, &applied, &packs, &desired) {
+        return Ok(false);
+    }
+
+    revert_folder(version_folder)?;
+    if desired.is_empty() {
+        return Ok(!applied.files.is_empty());
+    }
+
+    tracing::info!(folder = %version_folder.display(), ?packs, files = desired.len(), "applying content packs");
+    let backup = version_folder.join(BACKUP_FOLDER);
+    let mut applied = AppliedPacks { packs, files: BTreeMap::new() };
+    for (relative, file) in desired {
+        let target = content.join(&relative);
+        let original = backup.join(&relative);
+        let backed_up = target.exists();
+
+        /* Recorded before anything moves so revert can pick up from any point */
+        let record = AppliedFile { pack: file.pack, sha256: file.sha256, backed_up, source: file.stamp, target: None };
+        applied.files.insert(relative.clone(), record);
+        write_applied(version_folder, &applied)?;
+
+        if backed_up {
+            move_file(&target, &original)?;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&file.source, &target)?;
+
+        if let Some(record) = applied.files.get_mut(&relative) {
+            record.target = FileStamp::of(&target);
+        }
+    }
+
+    /* The copies are done so the stamps only need writing once */
+    write_applied(version_folder, &applied)?;
+    Ok(true)
+}
+
+pub fn apply_packs<T: AsRef<str>, V: AsRef<str>>(year: T, version: V) -> Result<bool> {
+    apply_to_folder(&player::get_client_folder(year, version)?)
+}
+
+pub fn revert_packs<T: AsRef<str>, V: AsRef<str>>(year: T, version: V) -> Result<()> {
+    revert_folder(&player::get_client_folder(year, version)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Setup {
+        _root: tempfile::TempDir,
+        packs: PathBuf,
+        version: PathBuf,
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn read(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    /* A version with two content files and one pack replacing one of them and adding another */
+    fn setup() -> Setup {
+        let root = tempfile::tempdir().unwrap();
+        let packs = root.path().join("packs");
+        let version = root.path().join("version");
+        write(&version.join("content/fonts/arial.ttf"), "original");
+        write(&version.join("content/sounds/ouch.ogg"), "ouch");
+        write(&packs.join("10-hd/fonts/arial.ttf"), "hd");
+        write(&packs.join("10-hd/textures/new.png"), "new");
+        Setup { packs, version, _root: root }
+    }
+
+    impl Setup {
+        fn content(&self, relative: &str) -> Option<String> {
+            read(&self.version.join("content").join(relative))
+        }
+
+        fn apply(&self) -> bool {
+            apply_from(&self.packs, &self.version).unwrap()
+        }
+
+        fn assert_original(&self) {
+            assert_eq!(self.content("fonts/arial.ttf").as_deref(), Some("original"));
+            assert_eq!(self.content("sounds/ouch.ogg").as_deref(), Some("ouch"));
+            assert_eq!(self.content("textures/new.png"), None);
+            assert!(!self.version.join(BACKUP_FOLDER).exists());
+            assert!(!self.version.join(APPLIED_FILE).exists());
+        }
+
+        fn assert_applied(&self) {
+            assert_eq!(self.content("fonts/arial.ttf").as_deref(), Some("hd"));
+            assert_eq!(self.content("sounds/ouch.ogg").as_deref(), Some("ouch"));
+            assert_eq!(self.content("textures/new.png").as_deref(), Some("new"));
+            assert_eq!(read(&self.version.join(BACKUP_FOLDER).join("fonts/arial.ttf")).as_deref(), Some("original"));
+        }
+    }
+
+    #[test]
+    fn apply_and_revert() {
+        let setup = setup();
+        assert!(setup.apply());
+        setup.assert_applied();
+        assert!(!setup.apply());
+
+        revert_folder(&setup.version).unwrap();
+        setup.assert_original();
+    }
+
+    #[test]
+    fn later_packs_win() {
+        let setup = setup();
+        write(&setup.packs.join("20-mine/fonts/arial.ttf"), "mine");
+        assert!(setup.apply());
+        assert_eq!(setup.content("fonts/arial.ttf").as_deref(), Some("mine"));
+        assert_eq!(read_applied(&setup.version).unwrap().files["fonts/arial.ttf"].pack, "20-mine");
+
+        /* Removing the pack goes back to the one under it */
+        fs::remove_dir_all(setup.packs.join("20-mine")).unwrap();
+        assert!(setup.apply());
+        setup.assert_applied();
+    }
+
+    #[test]
+    fn removing_every_pack_reverts() {
+        let setup = setup();
+        assert!(setup.apply());
+        fs::remove_dir_all(setup.packs.join("10-hd")).unwrap();
+        assert!(setup.apply());
+        setup.assert_original();
+    }
+
+    #[test]
+    fn content_changed_by_hand_is_reapplied() {
+        let setup = setup();
+        assert!(setup.apply());
+        write(&setup.version.join("content/fonts/arial.ttf"), "edited by hand");
+        assert!(setup.apply());
+        setup.assert_applied();
+    }
+
+    #[test]
+    fn hashes_only_changed_files() {
+        let setup = setup();
+        assert!(setup.apply());
+
+        /* A wrong hash with matching stamps is trusted, which shows nothing was hashed */
+        let mut applied = read_applied(&setup.version).unwrap();
+        for file in applied.files.values_mut() {
+            file.sha256 = "stale".into();
+        }
+        write_applied(&setup.version, &applied).unwrap();
+        assert!(!setup.apply());
+
+        /* A pack file with a new stamp is hashed again, finds the hash changed and is copied */
+        write(&setup.packs.join("10-hd/textures/new.png"), "newer!");
+        assert!(setup.apply());
+        assert_eq!(setup.content("textures/new.png").as_deref(), Some("newer!"));
+        assert_ne!(read_applied(&setup.version).unwrap().files["textures/new.png"].sha256, "stale");
+    }
+
+    #[test]
+    fn records_without_stamps_still_work() {
+        let setup = setup();
+        assert!(setup.apply());
+
+        let mut applied = read_applied(&setup.version).unwrap();
+        for file in applied.files.values_mut() {
+            file.source = None;
+            file.target = None;
+        }
+        write_applied(&setup.version, &applied).unwrap();
+        assert!(!setup.apply());
+    }
+
+    /* Applying stopped after the record was written but before the original moved */
+    #[test]
+    fn resumes_before_the_backup() {
+        let setup = setup();
+        let mut applied = AppliedPacks { packs: vec!["10-hd".into()], files: BTreeMap::new() };
+        applied.files.insert("fonts/arial.ttf".into(), AppliedFile {
+            pack: "10-hd".into(),
+            sha256: "unused".into(),
+            backed_up: true,
+            source: None,
+            target: None,
+        });
+        write_applied(&setup.version, &applied).unwrap();
+
+        revert_folder(&setup.version).unwrap();
+        setup.assert_original();
+    }
+
+    /* Applying stopped with the original moved and the pack file not copied yet */
+    #[test]
+    fn resumes_after_the_backup() {
+        let setup = setup();
+        let mut applied = AppliedPacks { packs: vec!["10-hd".into()], files: BTreeMap::new() };
+        for (relative, backed_up) in [("fonts/arial.ttf", true), ("textures/new.png", false)] {
+            applied.files.insert(relative.into(), AppliedFile {
+                pack: "10-hd".into(),
+                sha256: "unused".into(),
+                backed_up,
+                source: None,
+                target: None,
+            });
+        }
+        write_applied(&setup.version, &applied).unwrap();
+        move_file(&setup.version.join("content/fonts/arial.ttf"), &setup.version.join(BACKUP_FOLDER).join("fonts/arial.ttf")).unwrap();
+
+        assert!(setup.apply());
+        setup.assert_applied();
+        revert_folder(&setup.version).unwrap();
+        setup.assert_original();
+    }
+}
@@ -0,0 +1,113 @@
+use std::error::Error;
+use std::fmt::{ self, Display };
+
+use serde::Serialize;
+
+use super::secret::Secret;
+use super::Result;
+
+pub const PLAYER_SCHEME: &str = "syntax-player:";
+
+#[derive(Debug)]
+pub enum UriError {
+    NoLaunchArguments,
+    Missing(&'static str),
+}
+
+impl Display for UriError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UriError::NoLaunchArguments => write!(f, "No player launch arguments"),
+            UriError::Missing(field) => write!(f, "{} undefined", field),
+        }
+    }
+}
+
+impl Error for UriError {}
+
+/* Everything a syntax-player:// link carries, the ticket and join script never leave rust */
+#[derive(Debug)]
+pub struct PlayerLaunch {
+    pub launch_mode: String,
+    pub client_year: String,
+    pub auth_ticket: Secret,
+    pub join_script: Secret,
+}
+
+/* What the ui is allowed to see */
+#[derive(Debug, Serialize)]
+pub struct PlayerLaunchInfo {
+    launch_mode: String,
+    client_year: String,
+}
+
+impl PlayerLaunch {
+    pub fn info(&self) -> PlayerLaunchInfo {
+        PlayerLaunchInfo {
+            launch_mode: self.launch_mode.clone(),
+            client_year: self.client_year.clone(),
+        }
+    }
+}
+
+/* Arguments look like syntax-player://1+launchmode:play+gameinfo:TICKET+... */
+pub fn parse(uri: &str) -> Result<PlayerLaunch> {
+    if !uri.starts_with(PLAYER_SCHEME) {
+        return Err(UriError::NoLaunchArguments.into());
+    }
+
+    let mut launch_mode = None;
+    let mut client_year = None;
+    let mut auth_ticket = None;
+    let mut join_script = None;
+
+    for argument in uri.split('+') {
+        let Some((key, value)) = argument.split_once(':') else {
+            continue;
+        };
+
+        match key {
+            "launchmode" => {
+                launch_mode = Some(value.to_string());
+            }
+            "clientyear" => {
+                client_year = Some(value.to_string());
+            }
+            "gameinfo" => {
+                auth_ticket = Some(Secret::from(value));
+            }
+            "placelauncherurl" => {
+                join_script = Some(Secret::from(value));
+            }
+            _ => {}
+        }
+    }
+
+    let Some(launch_mode) = launch_mode else {
+        return Err(UriError::Missing("Launchmode").into());
+    };
+    let Some(auth_ticket) = auth_ticket else {
+        return Err(UriError::Missing("Authticket").into());
+    };
+    let Some(join_script) = join_script else {
+        return Err(UriError::Missing("Joinscript").into());
+    };
+    let Some(client_year) = client_year else {
+        return Err(UriError::Missing("Client year").into());
+    };
+
+    Ok(PlayerLaunch {
+        launch_mode,
+        client_year,
+        auth_ticket,
+        join_script,
+    })
+}
+
+/* The uri is always the first argument after the binary */
+pub fn from_args<S: AsRef<str>>(args: &[S]) -> Result<PlayerLaunch> {
+    let Some(uri) = args.get(1) else {
+        return Err(UriError::NoLaunchArguments.into());
+    };
+    parse(uri.as_ref())
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::fs::{ self, File, OpenOptions };
+use std::path::{ Path, PathBuf };
+use std::sync::Mutex;
+
+use fs2::FileExt;
+
+use super::Result;
+
+const LOCK_FILE: &str = ".install.lock";
+
+#[derive(Debug)]
+pub struct InstallBusy {
+    path: PathBuf,
+}
+
+impl Display for InstallBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is already being installed", self.path.display())
+    }
+}
+
+impl Error for InstallBusy {}
+
+/* Held while anything writes into an install folder, released on drop */
+pub struct InstallLock {
+    _file: File,
+}
+
+impl InstallLock {
+    pub fn acquire<P: AsRef<Path>>(dir: P) -> Result<InstallLock> {
+        let dir = dir.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE))?;
+
+        if file.try_lock_exclusive().is_err() {
+            return Err(InstallBusy { path: dir.to_path_buf() }.into());
+        }
+
+        Ok(InstallLock { _file: file })
+    }
+}
+
+/* Empties a folder under its lock, the lock file goes last since windows cant delete it while open */
+pub fn remove_locked<P: AsRef<Path>>(dir: P, lock: InstallLock) -> Result<()> {
+    let dir = dir.as_ref();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name() == LOCK_FILE {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    drop(lock);
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+/* Client installs span several commands so their locks are parked here between calls */
+static HELD: Mutex<Option<HashMap<PathBuf, InstallLock>>> = Mutex::new(None);
+
+pub fn hold<P: AsRef<Path>>(dir: P) -> Result<()> {
+    let dir = dir.as_ref();
+    let lock = InstallLock::acquire(dir)?;
+
+    let mut held = HELD.lock().map_err(|_| "Install locks poisoned")?;
+    held.get_or_insert_with(HashMap::new).insert(dir.to_path_buf(), lock);
+
+    Ok(())
+}
+
+pub fn release<P: AsRef<Path>>(dir: P) -> Result<()> {
+    let mut held = HELD.lock().map_err(|_| "Install locks poisoned")?;
+    if let Some(held) = held.as_mut() {
+        held.remove(dir.as_ref());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_blocks_a_second_acquire() {
+        let folder = tempfile::tempdir().unwrap();
+        let lock = InstallLock::acquire(folder.path()).unwrap();
+        assert!(InstallLock::acquire(folder.path()).is_err());
+
+        drop(lock);
+        assert!(InstallLock::acquire(folder.path()).is_ok());
+    }
+
+    #[test]
+    fn remove_locked_takes_everything() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = root.path().join("version-1");
+        fs::create_dir_all(folder.join("content/fonts")).unwrap();
+        fs::write(folder.join("RobloxPlayerBeta.exe"), "exe").unwrap();
+        fs::write(folder.join("content/fonts/a.ttf"), "font").unwrap();
+
+        let lock = InstallLock::acquire(&folder).unwrap();
+        remove_locked(&folder, lock).unwrap();
+        assert!(!folder.exists());
+    }
+}
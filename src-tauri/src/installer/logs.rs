@@ -0,0 +1,103 @@
+use std::fs::{ self, File };
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+
+use chrono::Local;
+
+use super::{ paths, Result };
+use crate::config;
+
+pub struct LaunchTag<'a> {
+    /* "client" or "studio" */
+    pub kind: &'a str,
+    pub year: &'a str,
+    pub version: Option<&'a str>,
+    pub mode: &'a str,
+}
+
+/* Keeps file names portable since versions and modes come from outside */
+fn sanitize(part: &str) -> String {
+    part.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' })
+        .collect()
+}
+
+impl LaunchTag<'_> {
+    fn file_name(&self, timestamp: &str) -> String {
+        let mut parts = vec![timestamp.to_string(), sanitize(self.kind), sanitize(self.year)];
+        if let Some(version) = self.version {
+            parts.push(sanitize(version));
+        }
+        parts.push(sanitize(self.mode));
+
+        format!("{}.log", parts.join("_"))
+    }
+}
+
+fn log_files() -> Result<Vec<PathBuf>> {
+    let mut logs = vec![];
+    for entry in fs::read_dir(paths::get_launch_logs_folder()?)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "log") {
+            logs.push(path);
+        }
+    }
+
+    /* Names start with the timestamp so sorting them sorts by age */
+    logs.sort();
+    Ok(logs)
+}
+
+pub fn rotate(keep: usize) -> Result<()> {
+    let logs = log_files()?;
+    if logs.len() <= keep {
+        return Ok(());
+    }
+
+    for old in &logs[..logs.len() - keep] {
+        fs::remove_file(old)?;
+    }
+
+    Ok(())
+}
+
+/* Creates the log the launched application writes its stdout and stderr into */
+pub fn create_launch_log(tag: &LaunchTag) -> Result<File> {
+    let now = Local::now();
+    let timestamp = now.format("%Y%m%d-%H%M%S").to_string();
+    let path = paths::get_launch_logs_folder()?.join(tag.file_name(&timestamp));
+    let mut log = File::create(path)?;
+
+    writeln!(log, "kind: {}", tag.kind)?;
+    writeln!(log, "year: {}", tag.year)?;
+    if let Some(version) = tag.version {
+        writeln!(log, "version: {}", version)?;
+    }
+    writeln!(log, "mode: {}", tag.mode)?;
+    writeln!(log, "started: {}", now.to_rfc3339())?;
+    writeln!(log)?;
+
+    /* The log we just made counts towards the limit */
+    rotate(config::get().retention.launch_logs)?;
+    Ok(log)
+}
+
+pub fn latest_launch_log() -> Result<Option<PathBuf>> {
+    Ok(log_files()?.pop())
+}
+
+pub fn export_latest<P: AsRef<Path>>(destination: P) -> Result<Option<PathBuf>> {
+    let Some(latest) = latest_launch_log()? else {
+        return Ok(None);
+    };
+    let destination = destination.as_ref();
+
+    /* Exporting into a folder keeps the original name */
+    let target = match (destination.is_dir(), latest.file_name()) {
+        (true, Some(name)) => destination.join(name),
+        _ => destination.to_path_buf(),
+    };
+
+    fs::copy(latest, &target)?;
+    Ok(Some(target))
+}
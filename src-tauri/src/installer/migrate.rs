@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use super::{ paths, space, Result };
+use super::lock::InstallLock;
+use crate::config;
+
+/* Everything under the install root that is worth moving, logs start fresh in the new root */
+const MIGRATED: [&str; 5] = ["clients", "studios", "Downloads", "plugins", "content-packs"];
+
+/* Copies land here first so a half finished copy never looks like a real install */
+const PARTIAL_EXTENSION: &str = "migrating";
+
+#[derive(Debug)]
+pub enum MigrateError {
+    Portable,
+    Relative,
+    SameRoot,
+    Nested,
+    AlreadyExists(PathBuf),
+    VerifyFailed(PathBuf),
+}
+
+impl Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::Portable => write!(f, "Portable installs always live beside the launcher, move its folder instead"),
+            MigrateError::Relative => write!(f, "The new install location must be an absolute path"),
+            MigrateError::SameRoot => write!(f, "Already installed there"),
+            MigrateError::Nested => write!(f, "The new install location cant be inside the old one or the other way around"),
+            MigrateError::AlreadyExists(path) => write!(f, "{} already exists", path.display()),
+            MigrateError::VerifyFailed(path) => write!(f, "Copy of {} did not match the original", path.display()),
+        }
+    }
+}
+
+impl Error for MigrateError {}
+
+fn folder_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += folder_size(&entry.path())?;
+        } else {
+            size += entry.metadata()?.len();
+        }
+    }
+
+    Ok(size)
+}
+
+fn copy_folder(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_folder(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/* Every file in the original has to exist in the copy with the same size */
+fn verify_copy(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            verify_copy(&entry.path(), &target)?;
+            continue;
+        }
+
+        let matches = fs
+            ::metadata(&target)
+            .map(|copied| copied.len() == entry.metadata().map(|m| m.len()).unwrap_or(u64::MAX))
+            .unwrap_or(false);
+        if !matches {
+            return Err(MigrateError::VerifyFailed(entry.path()).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn check_roots(old_root: &Path, new_root: &Path) -> Result<()> {
+    if !new_root.is_absolute() {
+        return Err(MigrateError::Relative.into());
+    }
+    if old_root == new_root {
+        return Err(MigrateError::SameRoot.into());
+    }
+    if new_root.starts_with(old_root) || old_root.starts_with(new_root) {
+        return Err(MigrateError::Nested.into());
+    }
+
+    for name in MIGRATED {
+        let target = new_root.join(name);
+        if target.exists() {
+            return Err(MigrateError::AlreadyExists(target).into());
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Client versions and studio years each have their own lock.
+    All of them are held for the move so nothing can be installing into a folder while it is copied
+*/
+fn lock_installs(old_root: &Path) -> Result<Vec<InstallLock>> {
+    let mut folders = vec![];
+    for (name, depth) in [("clients", 2), ("studios", 1)] {
+        collect_folders(&old_root.join(name), depth, &mut folders)?;
+    }
+
+    folders.iter().map(InstallLock::acquire).collect()
+}
+
+fn collect_folders(folder: &Path, depth: u32, folders: &mut Vec<PathBuf>) -> Result<()> {
+    if !folder.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if depth == 1 {
+            folders.push(entry.path());
+        } else {
+            collect_folders(&entry.path(), depth - 1, folders)?;
+        }
+    }
+    Ok(())
+}
+
+/* Folders taken from the old root, kept so the move can be undone */
+#[derive(Default)]
+struct Moved {
+    renamed: Vec<(PathBuf, PathBuf)>,
+    copied: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Moved {
+    fn undo(&self) {
+        for (from, to) in &self.renamed {
+            if let Err(e) = fs::rename(to, from) {
+                tracing::error!(error = %e, folder = %to.display(), "failed to undo install move");
+            }
+        }
+        for (_, to) in &self.copied {
+            let _ = fs::remove_dir_all(to);
+        }
+    }
+
+    /* The copied originals are only removed once the config points at the new root */
+    fn finish(self) {
+        for (from, _) in self.copied {
+            if let Err(e) = fs::remove_dir_all(&from) {
+                tracing::warn!(error = %e, folder = %from.display(), "failed to remove migrated folder");
+            }
+        }
+    }
+}
+
+fn move_folders(old_root: &Path, new_root: &Path) -> Result<Moved> {
+    let mut moved = Moved::default();
+    let mut to_copy = vec![];
+
+    /* Same filesystem moves are instant and need no extra space */
+    for name in MIGRATED {
+        let from = old_root.join(name);
+        let to = new_root.join(name);
+        if !from.exists() {
+            continue;
+        }
+
+        match fs::rename(&from, &to) {
+            Ok(()) => moved.renamed.push((from, to)),
+            Err(e) => {
+                tracing::info!(error = %e, folder = %from.display(), "rename failed, copying instead");
+                to_copy.push((from, to));
+            }
+        }
+    }
+
+    if let Err(e) = copy_folders(&to_copy, new_root) {
+        moved.undo();
+        return Err(e);
+    }
+
+    moved.copied = to_copy;
+    Ok(moved)
+}
+
+fn copy_folders(to_copy: &[(PathBuf, PathBuf)], new_root: &Path) -> Result<()> {
+    let mut required = 0;
+    for (from, _) in to_copy {
+        required += folder_size(from)?;
+    }
+    space::ensure(new_root, required)?;
+
+    let mut partials = vec![];
+    let result = (|| -> Result<()> {
+        for (from, to) in to_copy {
+            let partial = to.with_extension(PARTIAL_EXTENSION);
+            partials.push(partial.clone());
+
+            copy_folder(from, &partial)?;
+            verify_copy(from, &partial)?;
+        }
+
+        for ((_, to), partial) in to_copy.iter().zip(&partials) {
+            fs::rename(partial, to)?;
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        for partial in partials {
+            let _ = fs::remove_dir_all(partial);
+        }
+        for (_, to) in to_copy {
+            let _ = fs::remove_dir_all(to);
+        }
+    }
+    result
+}
+
+/*
+    Moves every install to a new root and switches the config over once everything is in place.
+    Nothing is deleted from the old root until the config has been saved
+*/
+pub fn migrate<P: AsRef<Path>>(new_root: P) -> Result<()> {
+    /* install_root is ignored in portable mode so the moved installs would never be found */
+    if paths::portable_root().is_some() {
+        return Err(MigrateError::Portable.into());
+    }
+
+    let new_root = new_root.as_ref().to_path_buf();
+    let old_root = paths::get_app_folder()?;
+    check_roots(&old_root, &new_root)?;
+
+    let locks = lock_installs(&old_root)?;
+    fs::create_dir_all(&new_root)?;
+    tracing::info!(from = %old_root.display(), to = %new_root.display(), installs = locks.len(), "migrating installs");
+
+    let moved = move_folders(&old_root, &new_root)?;
+
+    let mut config = config::get();
+    config.install_root = Some(new_root);
+    if let Err(e) = config::set(config) {
+        moved.undo();
+        return Err(e);
+    }
+
+    /* Windows cant delete the old folders while their lock files are open */
+    drop(locks);
+    moved.finish();
+    Ok(())
+}
@@ -1,19 +1,50 @@
-use std::{ error::Error, path::Path, fs::{ self, File }, io::Write, fmt::Display };
+use std::{ error::Error, path::{ Path, PathBuf }, fs::{ self, File }, io::{ self, Write }, fmt::Display };
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+use std::time::{ Duration, Instant };
 use futures_util::StreamExt;
 use rand::{ distributions::Alphanumeric, Rng };
-use std::process::Command;
+use sha2::{ Digest, Sha256 };
+use std::process::{ Child, Command, Stdio };
+use tokio::sync::Semaphore;
+
+use crate::config;
+use crate::logging::redact_args;
 
 pub mod uri;
 pub mod paths;
 pub mod studio;
 pub mod player;
+pub mod process;
+pub mod logs;
+pub mod secret;
+pub mod launch_uri;
+pub mod lock;
+pub mod space;
+pub mod migrate;
+pub mod uninstall;
+pub mod repair;
+pub mod places;
+pub mod plugins;
+pub mod client_settings;
+pub mod app_settings;
+pub mod content_packs;
+pub mod signing;
+pub mod self_update;
+#[cfg(target_os = "linux")]
+pub mod wine;
 
 pub const APP_NAME: &str = "RbTest";
+
+/* Defaults for the config, read the values through config::get() */
 pub const BASE_URL: &str = "www.rbtest.org";
 pub const SETUP_URL: &str = "setup.rbtest.org";
 
 pub const REPO_NAME: &str = "MojaveMF/rbtest";
 
+/* Kept in step with the version in tauri.conf.json */
+pub const LAUNCHER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[cfg(debug_assertions)]
 pub const TARGET_BRANCH: &str = "main";
 
@@ -22,42 +53,231 @@ pub const TARGET_BRANCH: &str = "release";
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
+/* Missing parts count as 0 so 1.2 and 1.2.0 are the same version */
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = version_parts(a);
+    let mut b = version_parts(b);
+    let length = a.len().max(b.len());
+    a.resize(length, 0);
+    b.resize(length, 0);
+
+    a.cmp(&b)
+}
+
 pub async fn latest_version() -> Result<String> {
-    Ok(reqwest::get(format!("https://{}/version", SETUP_URL)).await?.text().await?)
+    let setup_url = config::get().server().setup_url;
+    Ok(reqwest::get(format!("https://{}/version", setup_url)).await?.text().await?)
+}
+
+/* Sized once from the config, changing concurrency needs a restart */
+static DOWNLOAD_SLOTS: OnceLock<Semaphore> = OnceLock::new();
+
+fn download_slots() -> &'static Semaphore {
+    DOWNLOAD_SLOTS.get_or_init(|| Semaphore::new(config::get().concurrency))
+}
+
+/* Sleeps whenever a download gets ahead of its share of the bandwidth limit */
+struct Throttle {
+    bytes_per_second: Option<u64>,
+    started: Instant,
+    downloaded: u64,
+}
+
+impl Throttle {
+    fn new() -> Self {
+        let config = config::get();
+        Throttle {
+            bytes_per_second: config.bandwidth_limit.map(|limit|
+                (limit / (config.concurrency as u64)).max(1)
+            ),
+            started: Instant::now(),
+            downloaded: 0,
+        }
+    }
+
+    async fn consume(&mut self, bytes: usize) {
+        let Some(limit) = self.bytes_per_second else {
+            return;
+        };
+        self.downloaded += bytes as u64;
+
+        let expected = Duration::from_secs_f64((self.downloaded as f64) / (limit as f64));
+        let elapsed = self.started.elapsed();
+        if expected > elapsed {
+            tokio::time::sleep(expected - elapsed).await;
+        }
+    }
+}
+
+/* Read from the header since reqwest reports the empty body of a HEAD response */
+pub async fn remote_size<U: AsRef<str>>(url: U) -> Result<Option<u64>> {
+    let response = reqwest::Client::new().head(url.as_ref()).send().await?.error_for_status()?;
+    let size = response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse().ok());
+
+    Ok(size)
+}
+
+async fn download_size(urls: &[String]) -> u64 {
+    let mut size: u64 = 0;
+    for url in urls {
+        /* Servers that dont answer HEAD still get checked again once the download starts */
+        match remote_size(url).await {
+            Ok(remote) => {
+                size += remote.unwrap_or(0);
+            }
+            Err(e) => tracing::debug!(url, error = %e, "couldnt get download size"),
+        }
+    }
+
+    size
+}
+
+/* Only covers the downloads, what they unpack to is checked by ensure_extract_space once they are here */
+pub async fn ensure_space_for<P: AsRef<Path>>(urls: &[String], location: P) -> Result<()> {
+    space::ensure(location, download_size(urls).await)
+}
+
+/* Zips are usually far smaller than what they hold, so this goes by the sizes listed inside them */
+pub fn ensure_extract_space<Z: AsRef<Path>, P: AsRef<Path>>(zips: &[Z], location: P) -> Result<()> {
+    let mut required: u64 = 0;
+    for zip in zips {
+        required += zip_uncompressed_size(zip)?;
+    }
+
+    space::ensure(location, required)
+}
+
+pub fn zip_uncompressed_size<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let mut size = 0;
+    for i in 0..archive.len() {
+        size += archive.by_index_raw(i)?.size();
+    }
+
+    Ok(size)
 }
 
 pub async fn download_file<U: AsRef<str>, L: AsRef<Path>>(url: U, location: L) -> Result<()> {
     let url = url.as_ref();
     let file = location.as_ref();
+    let _slot = download_slots().acquire().await?;
+    tracing::info!(url, file = %file.display(), "downloading");
     let result = reqwest::get(url).await?;
 
+    if let Some(length) = result.content_length() {
+        space::ensure(file, length)?;
+    }
+
     let mut file = fs::File::create(file)?;
     let mut stream = result.bytes_stream();
+    let mut throttle = Throttle::new();
 
     while let Some(chunk) = stream.next().await {
         let chunk = chunk?;
         file.write_all(&chunk)?;
+        throttle.consume(chunk.len()).await;
     }
 
     Ok(())
 }
 
 pub async fn extract_zip<F: AsRef<Path>, T: AsRef<Path>>(from: F, to: T) -> Result<()> {
+    tracing::info!(zip = %from.as_ref().display(), to = %to.as_ref().display(), "extracting");
+    space::ensure(&to, zip_uncompressed_size(&from)?)?;
     zip_extract::extract(File::open(from)?, to.as_ref(), false)?;
     Ok(())
 }
 
-pub async fn download_and_extract<U: AsRef<str>, O: AsRef<Path>>(url: U, out: O) -> Result<()> {
-    let download_url = url.as_ref();
-    let file_name =
-        rand::thread_rng().sample_iter(&Alphanumeric).take(10).map(char::from).collect::<String>() +
-        ".zip";
-    let output_file = paths::get_downloads_folder()?.join(file_name);
+#[derive(Debug)]
+pub struct ChecksumMismatch {
+    expected: String,
+    actual: String,
+}
+
+impl Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Download checksum {} did not match {}", self.actual, self.expected)
+    }
+}
 
-    download_file(download_url, &output_file).await?;
-    extract_zip(output_file, out).await?;
+impl Error for ChecksumMismatch {}
 
-    Ok(())
+pub fn sha256_file<P: AsRef<Path>>(path: P) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    )
+}
+
+fn random_zip_name() -> String {
+    rand::thread_rng().sample_iter(&Alphanumeric).take(10).map(char::from).collect::<String>() + ".zip"
+}
+
+/*
+    Tries each source in order until one downloads and matches the checksum, then extracts it.
+    A known size skips asking the first source for it. Without an extracted size the
+    contents are only checked once the zip is here, by extract_zip
+*/
+pub async fn download_and_extract_from<O: AsRef<Path>>(
+    sources: &[String],
+    sha256: Option<&str>,
+    size: Option<u64>,
+    extracted_size: Option<u64>,
+    out: O
+) -> Result<()> {
+    let size = match size {
+        Some(size) => size,
+        None => download_size(&sources[..sources.len().min(1)]).await,
+    };
+    /* The zip is only removed after extracting so both are on disk at once */
+    space::ensure(&out, size.saturating_add(extracted_size.unwrap_or(0)))?;
+
+    let mut last_error: Box<dyn Error> = "No download sources".into();
+    for source in sources {
+        let output_file = paths::get_downloads_folder()?.join(random_zip_name());
+
+        let downloaded = download_file(source, &output_file).await.and_then(|_| {
+            let Some(expected) = sha256 else {
+                return Ok(());
+            };
+            let actual = sha256_file(&output_file)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(ChecksumMismatch { expected: expected.to_string(), actual }.into());
+            }
+            Ok(())
+        });
+
+        match downloaded {
+            Ok(()) => {
+                return extract_zip(output_file, out).await;
+            }
+            Err(e) => {
+                tracing::warn!(url = source, error = %e, "download failed, trying the next source");
+                let _ = fs::remove_file(&output_file);
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
 }
 
 pub async fn create_manifest_dirs<L: AsRef<Path>>(location: L, paths: Vec<&str>) -> Result<()> {
@@ -73,52 +293,198 @@ pub async fn create_manifest_dirs<L: AsRef<Path>>(location: L, paths: Vec<&str>)
     Ok(())
 }
 
-pub async fn download_from_repo<T: AsRef<str>>(file: T) -> Result<Vec<u8>> {
-    let file = file.as_ref();
+/* Detached signatures sit next to each metadata file with this appended */
+const SIGNATURE_EXTENSION: &str = ".sig";
+
+async fn fetch_from_repo(file: &str) -> Result<Vec<u8>> {
+    let metadata = config::get().metadata;
 
     let target_file = format!(
         "https://raw.githubusercontent.com/{}/{}/{}",
-        REPO_NAME,
-        TARGET_BRANCH,
+        metadata.repo,
+        metadata.branch,
         file
     );
 
-    Ok(reqwest::get(target_file).await?.bytes().await?.to_vec())
+    Ok(reqwest::get(target_file).await?.error_for_status()?.bytes().await?.to_vec())
 }
 
+/* Metadata decides what gets downloaded and run, so nothing is returned before its signature checks out */
+pub async fn download_from_repo<T: AsRef<str>>(file: T) -> Result<Vec<u8>> {
+    let file = file.as_ref();
+    let bytes = fetch_from_repo(file).await?;
+
+    /* Debug builds usually have no key, release builds dont compile without one */
+    if cfg!(debug_assertions) && !signing::has_key() {
+        tracing::warn!(file, "no signing key built in, metadata is not verified");
+        return Ok(bytes);
+    }
+
+    let signature = fetch_from_repo(&format!("{}{}", file, SIGNATURE_EXTENSION)).await?;
+    signing::verify(&signing::built_in_key()?, file, &bytes, &String::from_utf8_lossy(&signature))?;
+    Ok(bytes)
+}
+
+/* Only wine takes the exe as a string */
+#[cfg(target_os = "linux")]
 #[derive(Debug)]
 pub struct CouldntLocateExe;
 
+#[cfg(target_os = "linux")]
 impl Display for CouldntLocateExe {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Couldnt locate the binary")
     }
 }
 
+#[cfg(target_os = "linux")]
 impl Error for CouldntLocateExe {}
 
+/* Arguments are handed over as strings so paths have to be valid unicode */
+#[derive(Debug)]
+pub struct InvalidPath {
+    path: PathBuf,
+}
+
+impl Display for InvalidPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} cant be passed on since it is not valid unicode", self.path.display())
+    }
+}
+
+impl Error for InvalidPath {}
+
+/* Paths are translated for wine on linux, text is passed as it is */
+pub enum LaunchArg<'a> {
+    Text(&'a str),
+    Path(&'a Path),
+}
+
+impl<'a> From<&'a str> for LaunchArg<'a> {
+    fn from(text: &'a str) -> Self {
+        LaunchArg::Text(text)
+    }
+}
+
+impl<'a> From<&'a Path> for LaunchArg<'a> {
+    fn from(path: &'a Path) -> Self {
+        LaunchArg::Path(path)
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn resolve_args(args: &[LaunchArg]) -> Result<Vec<String>> {
+    args.iter()
+        .map(|arg| {
+            match arg {
+                LaunchArg::Text(text) => Ok(text.to_string()),
+                LaunchArg::Path(path) =>
+                    match path.to_str() {
+                        Some(path) => Ok(path.to_string()),
+                        None => Err(InvalidPath { path: path.to_path_buf() }.into()),
+                    }
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn resolve_args(args: &[LaunchArg]) -> Result<Vec<String>> {
+    args.iter()
+        .map(|arg| {
+            match arg {
+                LaunchArg::Text(text) => Ok(text.to_string()),
+                LaunchArg::Path(path) => wine::to_wine_path(path),
+            }
+        })
+        .collect()
+}
+
+/* Both streams share the one log so their output stays interleaved */
+fn log_output(log: File) -> Result<(Stdio, Stdio)> {
+    let stderr = log.try_clone()?;
+    Ok((log.into(), stderr.into()))
+}
+
 #[cfg(target_os = "windows")]
-pub fn launch_application<P: AsRef<Path>>(path: P, args: &[&str]) -> Result<()> {
+pub fn launch_application<P: AsRef<Path>>(path: P, args: &[LaunchArg], log: File) -> Result<Child> {
     let path = path.as_ref();
+    let args = resolve_args(args)?;
+    let (stdout, stderr) = log_output(log)?;
     let mut cmd = Command::new(path);
+    tracing::info!(exe = %path.display(), args = ?redact_args(&args), "launching");
 
     cmd.args(args);
-    cmd.spawn()?;
+    cmd.stdout(stdout);
+    cmd.stderr(stderr);
 
-    Ok(())
+    Ok(cmd.spawn()?)
 }
 
 #[cfg(target_os = "linux")]
-pub fn launch_application<P: AsRef<Path>>(path: P, args: &[&str]) -> Result<()> {
+pub fn launch_application<P: AsRef<Path>>(path: P, args: &[LaunchArg], log: File) -> Result<Child> {
     let path = path.as_ref();
     let Some(path_string) = path.to_str() else {
         return Err(CouldntLocateExe.into());
     };
+    let args = resolve_args(args)?;
+    let (stdout, stderr) = log_output(log)?;
+    let wine = config::get().wine;
 
-    let mut cmd = Command::new("wine");
+    let mut cmd = Command::new(&wine.binary);
+    tracing::info!(
+        runner = wine.binary,
+        exe = path_string,
+        args = ?redact_args(&args),
+        "launching through wine"
+    );
+    if let Some(prefix) = wine::configured_prefix()? {
+        cmd.env("WINEPREFIX", prefix);
+    }
+    cmd.args(&wine.args);
     cmd.arg(path_string);
     cmd.args(args);
-    cmd.spawn()?;
+    /* Lets pruning tell a running client apart, see process::in_use */
+    if let Some(folder) = path.parent() {
+        cmd.current_dir(folder);
+    }
+    cmd.stdout(stdout);
+    cmd.stderr(stderr);
+
+    Ok(cmd.spawn()?)
+}
+
+#[cfg(target_os = "windows")]
+pub fn open_path<P: AsRef<Path>>(path: P) -> Result<()> {
+    Command::new("explorer").arg(path.as_ref()).spawn()?;
+    Ok(())
+}
 
+#[cfg(target_os = "linux")]
+pub fn open_path<P: AsRef<Path>>(path: P) -> Result<()> {
+    Command::new("xdg-open").arg(path.as_ref()).spawn()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zip::{ write::FileOptions, CompressionMethod, ZipWriter };
+
+    #[test]
+    fn extract_space_uses_uncompressed_sizes() {
+        let folder = tempfile::tempdir().unwrap();
+        let zip_path = folder.path().join("content.zip");
+        let mut zip = ZipWriter::new(File::create(&zip_path).unwrap());
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.start_file("a.txt", options).unwrap();
+        zip.write_all(&[b'a'; 100_000]).unwrap();
+        zip.start_file("fonts/b.txt", options).unwrap();
+        zip.write_all(&[b'b'; 50_000]).unwrap();
+        zip.finish().unwrap();
+
+        assert!(fs::metadata(&zip_path).unwrap().len() < 10_000);
+        assert_eq!(zip_uncompressed_size(&zip_path).unwrap(), 150_000);
+        assert!(ensure_extract_space(&[&zip_path], folder.path()).is_ok());
+    }
+}
@@ -1,10 +1,13 @@
 use std::fmt::{ Display, self };
 use std::error::Error;
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{ Component, Path, PathBuf };
+use std::sync::OnceLock;
 use dirs;
 
 use super::APP_NAME;
+use crate::config;
 
 #[derive(Debug)]
 pub struct ErrNoPath {
@@ -19,6 +22,28 @@ impl Display for ErrNoPath {
 
 impl Error for ErrNoPath {}
 
+#[derive(Debug)]
+pub struct ErrBadName {
+    name: String,
+}
+
+impl Display for ErrBadName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not a valid folder name", self.name)
+    }
+}
+
+impl Error for ErrBadName {}
+
+/* Years and versions come from downloaded json, they have to stay a single folder inside ours */
+pub fn check_name(name: &str) -> Result<&str> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(part)), None) if part == name => Ok(name),
+        _ => Err(ErrBadName { name: name.to_string() }.into()),
+    }
+}
+
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
 fn dir_option_wrapper(dir: Option<PathBuf>) -> Result<PathBuf> {
@@ -38,7 +63,51 @@ fn dir_wrapper(path: PathBuf) -> Result<PathBuf> {
     return Ok(path);
 }
 
+/* A file with this name next to the binary turns on portable mode, same as passing the flag */
+const PORTABLE_MARKER: &str = "portable";
+pub const PORTABLE_FLAG: &str = "--portable";
+
+static PORTABLE_ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/*
+    Has to run before anything touches a path.
+    The flag is taken out of args so the ui still finds the uri or --studio at index 1
+*/
+pub fn init_portable(args: &mut Vec<String>) {
+    let flagged = args.iter().any(|arg| arg == PORTABLE_FLAG);
+    args.retain(|arg| arg != PORTABLE_FLAG);
+
+    let exe_dir = env
+        ::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()));
+
+    let root = exe_dir.and_then(|dir| {
+        if flagged || dir.join(PORTABLE_MARKER).exists() { Some(dir.join(APP_NAME)) } else { None }
+    });
+
+    let _ = PORTABLE_ROOT.set(root);
+}
+
+pub fn portable_root() -> Option<PathBuf> {
+    PORTABLE_ROOT.get().cloned().flatten()
+}
+
+/* Passed along by the uri handler and shortcuts so they start in portable mode too */
+pub fn portable_args() -> Vec<&'static str> {
+    if portable_root().is_some() { vec![PORTABLE_FLAG] } else { vec![] }
+}
+
 pub fn get_app_folder() -> Result<PathBuf> {
+    /* Portable installs ignore install_root since it would point off the drive */
+    if let Some(root) = portable_root() {
+        return dir_wrapper(root);
+    }
+
+    if let Some(root) = config::get().install_root {
+        return dir_wrapper(root);
+    }
+
     let dir = dir_option_wrapper(dirs::data_local_dir())?;
     let path = dir_wrapper(dir.join(APP_NAME))?;
     Ok(path)
@@ -62,6 +131,32 @@ pub fn get_studio_folder() -> Result<PathBuf> {
     dir_wrapper(studios)
 }
 
+/* Plugins kept per studio year, copied into each version of that year when it launches */
+pub fn get_plugins_folder() -> Result<PathBuf> {
+    dir_wrapper(get_app_folder()?.join("plugins"))
+}
+
+/* Each folder in here is a pack laid out like a clients content folder */
+pub fn get_content_packs_folder() -> Result<PathBuf> {
+    dir_wrapper(get_app_folder()?.join("content-packs"))
+}
+
+/* Only used in portable mode, otherwise wine picks its own prefix */
+pub fn get_portable_wine_prefix() -> Result<Option<PathBuf>> {
+    let Some(root) = portable_root() else {
+        return Ok(None);
+    };
+    Ok(Some(dir_wrapper(root.join("wine-prefix"))?))
+}
+
+pub fn get_logs_folder() -> Result<PathBuf> {
+    dir_wrapper(get_app_folder()?.join("logs"))
+}
+
+pub fn get_launch_logs_folder() -> Result<PathBuf> {
+    dir_wrapper(get_logs_folder()?.join("launches"))
+}
+
 #[cfg(target_os = "windows")]
 pub fn shortcut_path() -> Result<PathBuf> {
     let location = dir_option_wrapper(dirs::home_dir())?
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use super::{ paths, studio, uri, Result };
+use crate::config;
+
+/* The last studio year each place was opened with, keyed by its absolute path */
+const PLACE_YEARS_FILE: &str = "place-years.json";
+
+pub const PLACE_EXTENSIONS: [&str; 2] = ["rbxl", "rbxlx"];
+
+#[derive(Debug)]
+pub enum PlaceError {
+    NotAPlace(PathBuf),
+    NoStudio(PathBuf),
+}
+
+impl Display for PlaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaceError::NotAPlace(path) => write!(f, "{} is not a place file", path.display()),
+            PlaceError::NoStudio(path) =>
+                write!(f, "No studio installed to open {} with, set default_studio_year or install one", path.display()),
+        }
+    }
+}
+
+impl Error for PlaceError {}
+
+pub fn place_years_path() -> Result<PathBuf> {
+    Ok(config::config_folder()?.join(PLACE_YEARS_FILE))
+}
+
+/* A missing or broken file just means nothing was remembered yet */
+fn read_place_years() -> HashMap<PathBuf, String> {
+    place_years_path()
+        .ok()
+        .and_then(|path| fs::read(path).ok())
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/* Relative paths come from a shell so they are resolved against our working directory */
+pub fn place_path<P: AsRef<Path>>(file: P) -> Result<PathBuf> {
+    let file = file.as_ref();
+    let is_place = file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| PLACE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()));
+    if !is_place || !file.is_file() {
+        return Err(PlaceError::NotAPlace(file.to_path_buf()).into());
+    }
+
+    Ok(file.canonicalize()?)
+}
+
+/*
+    Makes the place in the arguments absolute before they can be forwarded,
+    the running launcher has its own working directory so a relative path would point somewhere else.
+    Anything that isnt a place is left for the ui to report
+*/
+pub fn resolve_place_args(args: &mut [String]) {
+    let index = match args.get(1).map(String::as_str) {
+        Some(uri::OPEN_PLACE_FLAG) => 2,
+        Some(uri::STUDIO_FLAG) => 3,
+        _ => {
+            return;
+        }
+    };
+    let Some(file) = args.get_mut(index) else {
+        return;
+    };
+
+    match place_path(&*file) {
+        Ok(place) => {
+            *file = place.to_string_lossy().into_owned();
+        }
+        Err(e) => tracing::warn!(file, error = %e, "couldnt resolve place file"),
+    }
+}
+
+pub fn remember_year(place: &Path, year: &str) -> Result<()> {
+    let mut years = read_place_years();
+    if years.get(place).is_some_and(|remembered| remembered == year) {
+        return Ok(());
+    }
+    years.insert(place.to_path_buf(), year.to_string());
+
+    let path = place_years_path()?;
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, serde_json::to_vec_pretty(&years)?)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+fn newest_installed_studio() -> Result<Option<String>> {
+    let studios = paths::get_studio_folder()?;
+    let mut years = vec![];
+    for entry in fs::read_dir(studios)? {
+        let entry = entry?;
+        if let Some(year) = entry.file_name().to_str() {
+            if entry.file_type()?.is_dir() && studio::is_installed(year) {
+                years.push(year.to_string());
+            }
+        }
+    }
+
+    years.sort();
+    Ok(years.pop())
+}
+
+/*
+    Picks the studio year for a place opened without one.
+    The year it was last opened with wins, then the configured default, then the newest installed studio
+*/
+pub fn resolve_year<P: AsRef<Path>>(file: P) -> Result<String> {
+    let place = place_path(file)?;
+
+    if let Some(year) = read_place_years().remove(&place) {
+        return Ok(year);
+    }
+    if let Some(year) = config::get().default_studio_year {
+        return Ok(year);
+    }
+    match newest_installed_studio()? {
+        Some(year) => Ok(year),
+        None => Err(PlaceError::NoStudio(place).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn place_args_become_absolute() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::create_dir(folder.path().join("places")).unwrap();
+        let place = folder.path().join("places").join("Happy Home.rbxl");
+        fs::write(&place, b"").unwrap();
+        let canonical = place.canonicalize().unwrap().to_string_lossy().into_owned();
+
+        let roundabout = folder.path().join("places").join("..").join("places").join("Happy Home.rbxl");
+        let mut args = vec!["syntax".to_string(), uri::OPEN_PLACE_FLAG.to_string(), roundabout.to_string_lossy().into_owned()];
+        resolve_place_args(&mut args);
+        assert_eq!(args[2], canonical);
+
+        let mut args = vec!["syntax".to_string(), uri::STUDIO_FLAG.to_string(), "2016".to_string(), roundabout.to_string_lossy().into_owned()];
+        resolve_place_args(&mut args);
+        assert_eq!(args, ["syntax", uri::STUDIO_FLAG, "2016", &canonical]);
+    }
+
+    #[test]
+    fn other_args_are_left_alone() {
+        let original = vec!["syntax".to_string(), uri::STUDIO_FLAG.to_string(), "2016".to_string(), "missing.rbxl".to_string()];
+        let mut args = original.clone();
+        resolve_place_args(&mut args);
+        assert_eq!(args, original);
+
+        let original = vec!["syntax".to_string(), "syntax-player://1+launchmode:play".to_string()];
+        let mut args = original.clone();
+        resolve_place_args(&mut args);
+        assert_eq!(args, original);
+
+        let mut args = vec!["syntax".to_string(), uri::OPEN_PLACE_FLAG.to_string()];
+        resolve_place_args(&mut args);
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn only_place_files() {
+        let folder = tempfile::tempdir().unwrap();
+        let script = folder.path().join("script.lua");
+        fs::write(&script, b"").unwrap();
+        assert!(place_path(&script).is_err());
+        assert!(place_path(folder.path().join("missing.rbxlx")).is_err());
+
+        let place = folder.path().join("Upper.RBXLX");
+        fs::write(&place, b"").unwrap();
+        assert_eq!(place_path(&place).unwrap(), place.canonicalize().unwrap());
+    }
+}
@@ -1,39 +1,25 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fs;
-use std::path::{ PathBuf, Path };
-
-use serde::Serialize;
-
-use crate::installer::launch_application;
-
-use super::{ paths, download_from_repo, create_manifest_dirs, BASE_URL };
+use std::path::PathBuf;
+
+use crate::installer::{ launch_application, LaunchArg };
+
+use super::{
+    paths,
+    download_from_repo,
+    create_manifest_dirs,
+    app_settings,
+    client_settings,
+    content_packs,
+    process,
+    logs,
+};
+use super::lock::{ self, InstallLock };
+use crate::config;
+use super::launch_uri::PlayerLaunch;
 use super::Result;
 
-#[derive(Debug, Serialize)]
-struct Settings {
-    #[serde(rename = "ContentFolder")]
-    content_folder: String,
-    #[serde(rename = "BaseUrl")]
-    base_url: String,
-}
-/* 
-    He returns !!!!
-*/
-pub async fn generate_appsettings<P: AsRef<Path>>(location: P) -> Result<()> {
-    let location = location.as_ref();
-    let settings = Settings {
-        content_folder: "content".into(),
-        base_url: BASE_URL.into(),
-    };
-
-    let encoded = serde_xml_rs::to_string(&settings)?;
-    let app_settings_path = location.join("AppSettings.xml");
-
-    fs::write(app_settings_path, encoded)?;
-
-    Ok(())
-}
-
 pub fn get_client_folder<T: AsRef<str>, V: AsRef<str>>(year: T, version: V) -> Result<PathBuf> {
     let dir = paths::get_clients_folder()?.join(year.as_ref());
     if !dir.exists() {
@@ -53,13 +39,13 @@ pub fn installed<T: AsRef<str>, V: AsRef<str>>(year: T, version: V) -> bool {
         return false;
     };
 
-    return folder.join("AppSettings.xml").exists();
+    return folder.join(app_settings::APP_SETTINGS_FILE).exists();
 }
 
 pub async fn get_client_manifest<T: AsRef<str>>(version: T) -> Result<HashMap<String, String>> {
     let version = version.as_ref();
     let bytes = download_from_repo(format!("data/manifest/{}.json", version)).await?;
-    println!("{}", String::from_utf8(bytes.clone()).unwrap());
+    tracing::debug!(version, manifest = %String::from_utf8_lossy(&bytes), "fetched client manifest");
     let hashmap = serde_json::from_slice(&bytes)?;
 
     Ok(hashmap)
@@ -92,11 +78,52 @@ pub async fn prepare_client<T: AsRef<str>, V: AsRef<str>>(
     Ok(())
 }
 
+/* Removes the oldest versions of a year, the version given is always kept */
+pub fn prune_versions<T: AsRef<str>, V: AsRef<str>>(year: T, keep_version: V) -> Result<()> {
+    let year_folder = paths::get_clients_folder()?.join(year.as_ref());
+    let keep_version = keep_version.as_ref();
+    let keep = config::get().retention.client_versions;
+
+    let mut versions = vec![];
+    for entry in fs::read_dir(&year_folder)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() || entry.file_name() == keep_version {
+            continue;
+        }
+        versions.push((entry.metadata()?.modified()?, entry.path()));
+    }
+
+    /* Newest first, the kept version takes up one of the slots */
+    versions.sort_by_key(|(modified, _)| Reverse(*modified));
+    for (_, old) in versions.into_iter().skip(keep.saturating_sub(1)) {
+        /* Versions being installed or still running are left for a later prune */
+        let lock = match InstallLock::acquire(&old) {
+            Ok(lock) => lock,
+            Err(e) => {
+                tracing::info!(folder = %old.display(), error = %e, "skipping locked client version");
+                continue;
+            }
+        };
+        if process::in_use(&old) {
+            tracing::info!(folder = %old.display(), "skipping running client version");
+            continue;
+        }
+
+        tracing::info!(folder = %old.display(), "removing old client version");
+        lock::remove_locked(&old, lock)?;
+    }
+
+    Ok(())
+}
+
 pub async fn launch_client<T: AsRef<str>, V: AsRef<str>>(
     year: T,
     version: V,
-    args: &[&str]
-) -> Result<()> {
+    launch: &PlayerLaunch,
+    crash_window: Option<u64>
+) -> Result<process::LaunchReport> {
+    let year = year.as_ref();
+    let version = version.as_ref();
     let client_folder = get_client_folder(year, version)?;
     let mut player_exe = client_folder.join("SyntaxPlayerBeta.exe");
 
@@ -104,6 +131,37 @@ pub async fn launch_client<T: AsRef<str>, V: AsRef<str>>(
         player_exe = client_folder.join("RobloxPlayerBeta.exe");
     }
 
-    launch_application(player_exe, args)?;
-    Ok(())
+    app_settings::apply(&client_folder)?;
+
+    /* A client that cant take the overrides still launches with what it shipped with */
+    if let Err(e) = client_settings::apply_flags(year, &client_folder) {
+        tracing::warn!(year, error = %e, "couldnt write client settings");
+    }
+    if let Err(e) = content_packs::apply_to_folder(&client_folder) {
+        tracing::warn!(year, error = %e, "couldnt apply content packs");
+    }
+
+    let log = logs::create_launch_log(
+        &(logs::LaunchTag {
+            kind: "client",
+            year,
+            version: Some(version),
+            mode: &launch.launch_mode,
+        })
+    )?;
+
+    let base_url = config::get().server().base_url;
+    let authentication_url = format!("https://{}/Login/Negotiate.ashx", base_url);
+    let args = [
+        "--play",
+        "--authenticationUrl",
+        &authentication_url,
+        "--authenticationTicket",
+        launch.auth_ticket.expose(),
+        "--joinScriptUrl",
+        launch.join_script.expose(),
+    ].map(LaunchArg::Text);
+    let child = launch_application(player_exe, &args, log)?;
+    process::report(child, crash_window).await
 }
+
@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{ Path, PathBuf };
+
+use serde::{ Deserialize, Serialize };
+
+use super::{ paths, studio, download_file, sha256_file, Result };
+
+/*
+    Plugins are kept per studio year outside the version folders so updating studio doesnt lose them.
+    They get copied into BuiltInPlugins of the version that launches, which every studio build loads from
+*/
+
+pub const PLUGIN_EXTENSIONS: [&str; 3] = ["rbxm", "rbxmx", "lua"];
+
+/* Lives next to the plugins and records where each one came from so it can be updated */
+const MANIFEST_FILE: &str = "plugins.json";
+
+/* Written into a version folder so plugins removed since the last launch get cleaned out of it */
+const DEPLOYED_FILE: &str = "deployed-plugins.json";
+
+const STUDIO_PLUGIN_FOLDER: &str = "BuiltInPlugins";
+
+#[derive(Debug)]
+pub enum PluginError {
+    NotAPlugin(String),
+    NotInstalled(String),
+    NoPlugins(PathBuf),
+}
+
+impl Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::NotAPlugin(source) =>
+                write!(f, "{} is not a plugin, expected one of {}", source, PLUGIN_EXTENSIONS.join(", ")),
+            PluginError::NotInstalled(name) => write!(f, "Plugin {} is not installed", name),
+            PluginError::NoPlugins(folder) => write!(f, "{} has no plugins in it", folder.display()),
+        }
+    }
+}
+
+impl Error for PluginError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plugin {
+    pub name: String,
+    /* A url or an absolute path */
+    pub source: String,
+    pub sha256: String,
+}
+
+type Manifest = BTreeMap<String, Plugin>;
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/* Names end up as file names so anything that could leave the folder is refused */
+fn plugin_name(source: &str) -> Result<String> {
+    let name = if is_url(source) {
+        let url = reqwest::Url::parse(source)?;
+        url.path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(|name| name.to_string())
+    } else {
+        Path::new(source)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_string())
+    };
+
+    let valid = name.as_deref().is_some_and(|name| {
+        let extension = Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        !name.starts_with('.') &&
+            !name.contains(['/', '\\']) &&
+            extension.is_some_and(|extension| PLUGIN_EXTENSIONS.contains(&extension.as_str()))
+    });
+
+    match name {
+        Some(name) if valid => Ok(name),
+        _ => Err(PluginError::NotAPlugin(source.to_string()).into()),
+    }
+}
+
+fn plugin_folder(year: &str) -> Result<PathBuf> {
+    let folder = paths::get_plugins_folder()?.join(year);
+    fs::create_dir_all(&folder)?;
+    Ok(folder)
+}
+
+fn read_manifest(folder: &Path) -> Result<Manifest> {
+    match fs::read(folder.join(MANIFEST_FILE)) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Manifest::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_manifest(folder: &Path, manifest: &Manifest) -> Result<()> {
+    let path = folder.join(MANIFEST_FILE);
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, serde_json::to_vec_pretty(manifest)?)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+/* Fetched next to the plugin first so a failed download never replaces a working one */
+async fn fetch(source: &str, target: &Path) -> Result<String> {
+    let partial = target.with_extension("partial");
+    let fetched = if is_url(source) {
+        download_file(source, &partial).await
+    } else {
+        fs::copy(source, &partial).map(|_| ()).map_err(|e| e.into())
+    };
+
+    let hashed = fetched.and_then(|_| sha256_file(&partial));
+    let sha256 = match hashed {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    fs::rename(partial, target)?;
+    Ok(sha256)
+}
+
+/* Every plugin directly inside a folder, subfolders are left alone */
+fn plugins_in(folder: &Path) -> Result<Vec<String>> {
+    let mut sources = vec![];
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let source = entry.path().canonicalize()?.to_string_lossy().to_string();
+        if plugin_name(&source).is_ok() {
+            sources.push(source);
+        }
+    }
+
+    if sources.is_empty() {
+        return Err(PluginError::NoPlugins(folder.to_path_buf()).into());
+    }
+    sources.sort();
+    Ok(sources)
+}
+
+/*
+    Installs a plugin from a url, a file or every plugin in a folder.
+    Installing a name that already exists replaces it. Returns the names installed
+*/
+pub async fn install_plugins<T: AsRef<str>>(year: T, source: &str) -> Result<Vec<String>> {
+    let year = year.as_ref();
+    let sources = if is_url(source) {
+        vec![source.to_string()]
+    } else {
+        let path = Path::new(source);
+        if path.is_dir() {
+            plugins_in(path)?
+        } else {
+            vec![path.canonicalize()?.to_string_lossy().to_string()]
+        }
+    };
+
+    let folder = plugin_folder(year)?;
+    let mut manifest = read_manifest(&folder)?;
+    let mut installed = vec![];
+    for source in sources {
+        let name = plugin_name(&source)?;
+        tracing::info!(year, plugin = name, source, "installing studio plugin");
+        let sha256 = fetch(&source, &folder.join(&name)).await?;
+
+        manifest.insert(name.clone(), Plugin { name: name.clone(), source, sha256 });
+        installed.push(name);
+    }
+
+    write_manifest(&folder, &manifest)?;
+    sync_plugins(year)?;
+    Ok(installed)
+}
+
+/*
+    Fetches every plugin again from where it was installed from and returns the ones that changed.
+    A source that went away only logs so the copy we have keeps working
+*/
+pub async fn update_plugins<T: AsRef<str>>(year: T) -> Result<Vec<String>> {
+    let year = year.as_ref();
+    let folder = plugin_folder(year)?;
+    let mut manifest = read_manifest(&folder)?;
+    let mut updated = vec![];
+
+    for plugin in manifest.values_mut() {
+        let sha256 = match fetch(&plugin.source, &folder.join(&plugin.name)).await {
+            Ok(sha256) => sha256,
+            Err(e) => {
+                tracing::warn!(year, plugin = plugin.name, error = %e, "couldnt update studio plugin");
+                continue;
+            }
+        };
+
+        if sha256 != plugin.sha256 {
+            tracing::info!(year, plugin = plugin.name, "updated studio plugin");
+            plugin.sha256 = sha256;
+            updated.push(plugin.name.clone());
+        }
+    }
+
+    write_manifest(&folder, &manifest)?;
+    sync_plugins(year)?;
+    Ok(updated)
+}
+
+pub fn remove_plugin<T: AsRef<str>>(year: T, name: &str) -> Result<()> {
+    let year = year.as_ref();
+    let folder = plugin_folder(year)?;
+    let mut manifest = read_manifest(&folder)?;
+    if manifest.remove(name).is_none() {
+        return Err(PluginError::NotInstalled(name.to_string()).into());
+    }
+
+    tracing::info!(year, plugin = name, "removing studio plugin");
+    match fs::remove_file(folder.join(name)) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e.into());
+        }
+    }
+
+    write_manifest(&folder, &manifest)?;
+    sync_plugins(year)
+}
+
+pub fn list_plugins<T: AsRef<str>>(year: T) -> Result<Vec<Plugin>> {
+    let folder = plugin_folder(year.as_ref())?;
+    Ok(read_manifest(&folder)?.into_values().collect())
+}
+
+/*
+    Copies the manifests plugins into the versions BuiltInPlugins and takes out ones that were removed.
+    Only files listed in deployed-plugins.json are ours, anything else there shipped with studio and is never replaced or removed
+*/
+fn deploy(folder: &Path, manifest: &Manifest, install_folder: &Path) -> Result<()> {
+    let target = install_folder.join(STUDIO_PLUGIN_FOLDER);
+    let deployed_path = install_folder.join(DEPLOYED_FILE);
+    let deployed: Vec<String> = fs
+        ::read(&deployed_path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+
+    for name in deployed.iter().filter(|name| !manifest.contains_key(*name)) {
+        match fs::remove_file(target.join(name)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+
+    if !manifest.is_empty() {
+        fs::create_dir_all(&target)?;
+    }
+    let mut names = vec![];
+    for plugin in manifest.values() {
+        let destination = target.join(&plugin.name);
+        if destination.exists() && !deployed.contains(&plugin.name) {
+            tracing::warn!(plugin = plugin.name, "studio ships a plugin with this name, not replacing it");
+            continue;
+        }
+
+        names.push(&plugin.name);
+        if sha256_file(&destination).is_ok_and(|sha256| sha256 == plugin.sha256) {
+            continue;
+        }
+        fs::copy(folder.join(&plugin.name), destination)?;
+    }
+
+    fs::write(deployed_path, serde_json::to_vec_pretty(&names)?)?;
+    Ok(())
+}
+
+/* Copies the years plugins into the version launches use */
+pub fn sync_plugins<T: AsRef<str>>(year: T) -> Result<()> {
+    let year = year.as_ref();
+    let Some(install_folder) = studio::get_install_folder(year)? else {
+        return Ok(());
+    };
+
+    let folder = plugin_folder(year)?;
+    deploy(&folder, &read_manifest(&folder)?, &install_folder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Setup {
+        _root: tempfile::TempDir,
+        store: PathBuf,
+        install: PathBuf,
+    }
+
+    fn setup() -> Setup {
+        let root = tempfile::tempdir().unwrap();
+        let store = root.path().join("plugins");
+        let install = root.path().join("version");
+        fs::create_dir_all(&store).unwrap();
+        fs::create_dir_all(install.join(STUDIO_PLUGIN_FOLDER)).unwrap();
+        Setup { store, install, _root: root }
+    }
+
+    fn add(setup: &Setup, manifest: &mut Manifest, name: &str, content: &str) {
+        let path = setup.store.join(name);
+        fs::write(&path, content).unwrap();
+        let plugin = Plugin { name: name.into(), source: path.to_string_lossy().into(), sha256: sha256_file(&path).unwrap() };
+        manifest.insert(name.into(), plugin);
+    }
+
+    fn built_in(setup: &Setup, name: &str) -> PathBuf {
+        setup.install.join(STUDIO_PLUGIN_FOLDER).join(name)
+    }
+
+    #[test]
+    fn deploys_and_removes_our_plugins() {
+        let setup = setup();
+        let mut manifest = Manifest::new();
+        add(&setup, &mut manifest, "Tool.rbxm", "one");
+        deploy(&setup.store, &manifest, &setup.install).unwrap();
+        assert_eq!(fs::read_to_string(built_in(&setup, "Tool.rbxm")).unwrap(), "one");
+
+        add(&setup, &mut manifest, "Tool.rbxm", "two");
+        deploy(&setup.store, &manifest, &setup.install).unwrap();
+        assert_eq!(fs::read_to_string(built_in(&setup, "Tool.rbxm")).unwrap(), "two");
+
+        manifest.clear();
+        deploy(&setup.store, &manifest, &setup.install).unwrap();
+        assert!(!built_in(&setup, "Tool.rbxm").exists());
+    }
+
+    #[test]
+    fn never_replaces_shipped_plugins() {
+        let setup = setup();
+        fs::write(built_in(&setup, "Terrain.rbxm"), "shipped").unwrap();
+
+        let mut manifest = Manifest::new();
+        add(&setup, &mut manifest, "Terrain.rbxm", "ours");
+        deploy(&setup.store, &manifest, &setup.install).unwrap();
+        assert_eq!(fs::read_to_string(built_in(&setup, "Terrain.rbxm")).unwrap(), "shipped");
+
+        /* Not recorded as ours, so removing it from the manifest leaves studios file alone too */
+        manifest.clear();
+        deploy(&setup.store, &manifest, &setup.install).unwrap();
+        assert_eq!(fs::read_to_string(built_in(&setup, "Terrain.rbxm")).unwrap(), "shipped");
+    }
+}
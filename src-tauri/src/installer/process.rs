@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::Path;
+use std::process::Child;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use serde::Serialize;
+
+use super::Result;
+
+/* How often we check if the child has exited */
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Serialize)]
+pub struct LaunchReport {
+    /* The child was still alive once we stopped watching it */
+    running: bool,
+    exit_code: Option<i32>,
+    /* Exited before the crash window closed */
+    crashed: bool,
+    uptime_ms: u128,
+}
+
+impl LaunchReport {
+    pub fn detached() -> Self {
+        LaunchReport {
+            running: true,
+            exit_code: None,
+            crashed: false,
+            uptime_ms: 0,
+        }
+    }
+}
+
+/*
+    Watches the child until it exits or the crash window passes.
+    Anything that dies inside the window is treated as a crash since clients never exit on their own that fast
+*/
+pub async fn supervise(mut child: Child, crash_window: Duration) -> Result<LaunchReport> {
+    let started = Instant::now();
+
+    loop {
+        let uptime = started.elapsed();
+
+        if let Some(status) = child.try_wait()? {
+            tracing::info!(code = ?status.code(), uptime_ms = uptime.as_millis(), "launched application exited");
+            return Ok(LaunchReport {
+                running: false,
+                exit_code: status.code(),
+                crashed: uptime < crash_window,
+                uptime_ms: uptime.as_millis(),
+            });
+        }
+
+        if uptime >= crash_window {
+            reap(child);
+            return Ok(LaunchReport {
+                running: true,
+                exit_code: None,
+                crashed: false,
+                uptime_ms: uptime.as_millis(),
+            });
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/* None means the caller didnt want the child supervised */
+pub async fn report(child: Child, crash_window: Option<u64>) -> Result<LaunchReport> {
+    match crash_window {
+        Some(seconds) => supervise(child, Duration::from_secs(seconds)).await,
+        None => {
+            reap(child);
+            Ok(LaunchReport::detached())
+        }
+    }
+}
+
+/* Clients and studio under wine are started from their own folder, so any process working in it is one */
+#[cfg(target_os = "linux")]
+pub fn in_use(folder: &Path) -> bool {
+    let Ok(folder) = folder.canonicalize() else {
+        return false;
+    };
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return false;
+    };
+
+    processes
+        .flatten()
+        .filter_map(|process| fs::read_link(process.path().join("cwd")).ok())
+        .any(|cwd| cwd.starts_with(&folder))
+}
+
+/*
+    A running exe cant be opened without sharing, this works while the install lock is held too.
+    Subfolders are checked as well since a studio year keeps each build in its own
+*/
+#[cfg(target_os = "windows")]
+pub fn in_use(folder: &Path) -> bool {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    let Ok(entries) = fs::read_dir(folder) else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            return in_use(&path);
+        }
+        path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("exe")) &&
+            fs::OpenOptions::new().read(true).share_mode(0).open(path).is_err()
+    })
+}
+
+/* Once we stop watching, something still has to wait on the child or it is left as a zombie */
+fn reap(mut child: Child) {
+    let spawned = thread::Builder::new().name("reap-child".into()).spawn(move || {
+        match child.wait() {
+            Ok(status) => tracing::info!(code = ?status.code(), "launched application exited"),
+            Err(e) => tracing::warn!(error = %e, "couldnt wait on launched application"),
+        }
+    });
+
+    if let Err(e) = spawned {
+        tracing::warn!(error = %e, "couldnt start a thread to reap the launched application");
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn running_client_is_in_use() {
+        let folder = tempfile::tempdir().unwrap();
+        let version = folder.path().join("version-1");
+        fs::create_dir(&version).unwrap();
+        assert!(!in_use(&version));
+
+        let mut child = Command::new("sleep").arg("30").current_dir(&version).spawn().unwrap();
+        assert!(in_use(&version));
+        assert!(!in_use(folder.path().join("version-2").as_path()));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!in_use(&version));
+    }
+
+    #[test]
+    fn reaped_child_leaves_no_zombie() {
+        let child = Command::new("true").spawn().unwrap();
+        let proc = Path::new("/proc").join(child.id().to_string());
+        reap(child);
+
+        let started = Instant::now();
+        while proc.exists() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!proc.exists());
+    }
+}
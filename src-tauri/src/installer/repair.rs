@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use serde::Serialize;
+
+use super::{ app_settings, paths, studio, Result };
+
+/* Every finished install has this, a folder without it was left behind by a failed install */
+const INSTALLED_MARKER: &str = app_settings::APP_SETTINGS_FILE;
+
+#[derive(Debug, Serialize)]
+pub struct BrokenClient {
+    year: String,
+    version: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BrokenInstalls {
+    clients: Vec<BrokenClient>,
+    studios: Vec<String>,
+}
+
+fn subfolders(path: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut folders = vec![];
+    if !path.exists() {
+        return Ok(folders);
+    }
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(name) = entry.file_name().to_str() {
+            folders.push((name.to_string(), entry.path()));
+        }
+    }
+
+    Ok(folders)
+}
+
+fn is_broken(folder: &Path) -> bool {
+    !folder.join(INSTALLED_MARKER).exists()
+}
+
+/* Only reads the folders, fixing them is left to the ui which already knows how to install */
+pub fn find_broken() -> Result<BrokenInstalls> {
+    let mut broken = BrokenInstalls::default();
+
+    for (year, year_folder) in subfolders(&paths::get_clients_folder()?)? {
+        for (version, version_folder) in subfolders(&year_folder)? {
+            if is_broken(&version_folder) {
+                broken.clients.push(BrokenClient { year: year.clone(), version });
+            }
+        }
+    }
+
+    /* Studios keep a verified current version, a year without one never finished installing */
+    for (year, _) in subfolders(&paths::get_studio_folder()?)? {
+        if !studio::is_installed(&year) {
+            broken.studios.push(year);
+        }
+    }
+
+    tracing::info!(clients = broken.clients.len(), studios = broken.studios.len(), "checked installs");
+    Ok(broken)
+}
@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{ Path, PathBuf };
+use std::time::Duration;
+
+use serde::{ Deserialize, Serialize };
+
+use super::{ signing, uri, download_file, compare_versions, LAUNCHER_VERSION, SIGNATURE_EXTENSION, Result };
+use crate::config;
+
+/*
+    The new launcher is downloaded next to the running one and only swapped in once its signature checks out.
+    The running launcher keeps going from the renamed file, the next launch starts the new one
+*/
+
+/* Served from the setup host of the active server profile */
+const FEED_PATH: &str = "launcher/latest.json";
+
+/* Last launcher version that ran, in the config folder. A different one means we were just updated */
+pub const VERSION_MARKER: &str = "launcher-version";
+
+/* Updates run in the background while the launcher waits to exit, so neither step may hang */
+const FEED_TIMEOUT: Duration = Duration::from_secs(15);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+const NEW_EXTENSION: &str = "new";
+const OLD_EXTENSION: &str = "old";
+
+/* Platforms are keyed like windows-x86_64, same as the tauri updater */
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    url: String,
+    /* Hex ed25519 signature of the downloaded file, signed as asset_path */
+    signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseFeed {
+    version: String,
+    #[serde(default)]
+    notes: String,
+    #[serde(default)]
+    platforms: HashMap<String, ReleaseAsset>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LauncherUpdate {
+    pub version: String,
+    pub notes: String,
+}
+
+fn platform() -> String {
+    format!("{}-{}", env::consts::OS, env::consts::ARCH)
+}
+
+/* What a release binary is signed as, so one platforms build cant be passed off as another or as another version */
+fn asset_path(version: &str) -> String {
+    format!("launcher/{}/{}", version, platform())
+}
+
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    Ok(client.get(url).send().await?.error_for_status()?.bytes().await?.to_vec())
+}
+
+/*
+    None when we are up to date or the feed has no build for this platform.
+    The feed is signed as a whole so an old release cant be served under a newer version
+*/
+async fn available_release() -> Result<Option<(ReleaseFeed, ReleaseAsset)>> {
+    /* Nothing could be installed without a key to check it with */
+    if !signing::has_key() {
+        return Ok(None);
+    }
+
+    let setup_url = config::get().server().setup_url;
+    let url = format!("https://{}/{}", setup_url, FEED_PATH);
+    let client = reqwest::Client::builder().timeout(FEED_TIMEOUT).build()?;
+    let bytes = fetch(&client, &url).await?;
+    let signature = fetch(&client, &format!("{}{}", url, SIGNATURE_EXTENSION)).await?;
+    signing::verify(&signing::built_in_key()?, FEED_PATH, &bytes, &String::from_utf8_lossy(&signature))?;
+    let mut feed: ReleaseFeed = serde_json::from_slice(&bytes)?;
+
+    if compare_versions(&feed.version, LAUNCHER_VERSION).is_le() {
+        return Ok(None);
+    }
+    let Some(asset) = feed.platforms.remove(&platform()) else {
+        tracing::info!(version = feed.version, platform = platform(), "launcher update has no build for this platform");
+        return Ok(None);
+    };
+
+    Ok(Some((feed, asset)))
+}
+
+pub async fn check() -> Result<Option<LauncherUpdate>> {
+    Ok(available_release().await?.map(|(feed, _)| LauncherUpdate { version: feed.version, notes: feed.notes }))
+}
+
+/* An AppImage runs from a mount, the file to replace is the image itself */
+#[cfg(target_os = "linux")]
+fn launcher_path() -> Result<PathBuf> {
+    match env::var_os("APPIMAGE") {
+        Some(image) => Ok(PathBuf::from(image)),
+        None => Ok(env::current_exe()?),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn launcher_path() -> Result<PathBuf> {
+    Ok(env::current_exe()?)
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+fn remove_file_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/* Windows wont replace a running exe but lets it be renamed, so both platforms move the old one aside */
+fn swap(launcher: &Path, new: &Path) -> Result<()> {
+    let old = sibling(launcher, OLD_EXTENSION);
+    remove_file_if_exists(&old)?;
+    fs::rename(launcher, &old)?;
+
+    if let Err(e) = fs::rename(new, launcher) {
+        let _ = fs::rename(&old, launcher);
+        let _ = fs::remove_file(new);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/* Returns the version installed, None when there was nothing newer or no key to check it with */
+pub async fn install() -> Result<Option<String>> {
+    let Some((feed, asset)) = available_release().await? else {
+        return Ok(None);
+    };
+
+    let launcher = launcher_path()?;
+    let new = sibling(&launcher, NEW_EXTENSION);
+    tracing::info!(version = feed.version, url = asset.url, "downloading launcher update");
+
+    let downloaded = match tokio::time::timeout(DOWNLOAD_TIMEOUT, download_file(&asset.url, &new)).await {
+        Ok(downloaded) => downloaded,
+        Err(_) => Err("Launcher update download timed out".into()),
+    };
+    let verified = downloaded.and_then(|_| {
+        signing::verify(&signing::built_in_key()?, &asset_path(&feed.version), &fs::read(&new)?, &asset.signature)?;
+        make_executable(&new)
+    });
+    if let Err(e) = verified {
+        let _ = fs::remove_file(&new);
+        return Err(e);
+    }
+
+    swap(&launcher, &new)?;
+    tracing::info!(version = feed.version, "launcher updated, it is used from the next launch");
+    Ok(Some(feed.version))
+}
+
+/*
+    Runs on every start. The first time a new version runs it removes the old binary
+    and writes the uri, place files and shortcuts again since they are in this versions format.
+    Returns true when that happened
+*/
+pub async fn finish_update() -> Result<bool> {
+    /* Windows can still have the old exe open for a moment, it gets another go next start */
+    if let Err(e) = remove_file_if_exists(&sibling(&launcher_path()?, OLD_EXTENSION)) {
+        tracing::warn!(error = %e, "couldnt remove the previous launcher");
+    }
+
+    let marker = config::config_folder()?.join(VERSION_MARKER);
+    let previous = fs::read_to_string(&marker).ok();
+    if previous.as_deref().map(str::trim) == Some(LAUNCHER_VERSION) {
+        return Ok(false);
+    }
+
+    /* A first install registers through the ui, only an update has anything to redo */
+    if let Some(previous) = &previous {
+        tracing::info!(from = previous.trim(), to = LAUNCHER_VERSION, "re-registering after update");
+        uri::register_uri().await?;
+        uri::register_place_files(false).await?;
+        uri::refresh_studio_shortcuts().await?;
+    }
+
+    fs::write(marker, LAUNCHER_VERSION)?;
+    Ok(previous.is_some())
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt::{ self, Display };
+
+use ed25519_dalek::{ Signature, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH };
+
+use super::Result;
+
+/*
+    SYNTAX_SIGNING_KEY is the hex encoded ed25519 public key, a release build fails without it.
+    Signatures are hex too, made over the path of the file, a newline and then the exact bytes downloaded.
+    The path stops a signed file from being served in place of another
+*/
+#[cfg(debug_assertions)]
+const SIGNING_KEY: Option<&str> = option_env!("SYNTAX_SIGNING_KEY");
+#[cfg(not(debug_assertions))]
+const SIGNING_KEY: Option<&str> = Some(env!("SYNTAX_SIGNING_KEY", "Release builds need SYNTAX_SIGNING_KEY, see Signing in the README"));
+
+#[derive(Debug)]
+pub enum SigningError {
+    NoKey,
+    InvalidKey,
+    MalformedSignature,
+    BadSignature(String),
+}
+
+impl Display for SigningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SigningError::NoKey => write!(f, "This launcher was built without a signing key"),
+            SigningError::InvalidKey => write!(f, "The signing key built into this launcher is invalid"),
+            SigningError::MalformedSignature => write!(f, "Signature is not a hex encoded ed25519 signature"),
+            SigningError::BadSignature(what) => write!(f, "Signature of {} did not match", what),
+        }
+    }
+}
+
+impl Error for SigningError {}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub fn has_key() -> bool {
+    SIGNING_KEY.is_some()
+}
+
+pub fn built_in_key() -> Result<VerifyingKey> {
+    let Some(key) = SIGNING_KEY else {
+        return Err(SigningError::NoKey.into());
+    };
+    let bytes: [u8; PUBLIC_KEY_LENGTH] = decode_hex(key)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SigningError::InvalidKey)?;
+
+    VerifyingKey::from_bytes(&bytes).map_err(|_| SigningError::InvalidKey.into())
+}
+
+fn signed_message(path: &str, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(path.len() + 1 + data.len());
+    message.extend_from_slice(path.as_bytes());
+    message.push(b'\n');
+    message.extend_from_slice(data);
+    message
+}
+
+/* Path is what the file was fetched as, eg data/clients.json, and names it in the error */
+pub fn verify(key: &VerifyingKey, path: &str, data: &[u8], signature: &str) -> Result<()> {
+    let bytes: [u8; SIGNATURE_LENGTH] = decode_hex(signature)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SigningError::MalformedSignature)?;
+
+    /* Strict so a signature cant be tweaked into a second valid one */
+    key
+        .verify_strict(&signed_message(path, data), &Signature::from_bytes(&bytes))
+        .map_err(|_| SigningError::BadSignature(path.to_string()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{ Signer, SigningKey };
+
+    fn keypair() -> (SigningKey, VerifyingKey) {
+        let signing = SigningKey::from_bytes(&rand::random());
+        let verifying = signing.verifying_key();
+        (signing, verifying)
+    }
+
+    fn sign(key: &SigningKey, path: &str, data: &[u8]) -> String {
+        key.sign(&signed_message(path, data))
+            .to_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_good_signature() {
+        let (signing, verifying) = keypair();
+        let signature = sign(&signing, "data/clients.json", b"[\"2016\"]");
+        verify(&verifying, "data/clients.json", b"[\"2016\"]", &signature).unwrap();
+        /* Whitespace from the .sig file is ignored, so is the case of the hex */
+        verify(&verifying, "data/clients.json", b"[\"2016\"]", &format!("{}\n", signature.to_uppercase())).unwrap();
+    }
+
+    #[test]
+    fn rejects_changed_data() {
+        let (signing, verifying) = keypair();
+        let signature = sign(&signing, "data/clients.json", b"[\"2016\"]");
+        let error = verify(&verifying, "data/clients.json", b"[\"2017\"]", &signature).unwrap_err();
+        assert!(matches!(error.downcast_ref::<SigningError>(), Some(SigningError::BadSignature(_))));
+    }
+
+    #[test]
+    fn rejects_a_signature_for_another_path() {
+        let (signing, verifying) = keypair();
+        let signature = sign(&signing, "data/clients.json", b"{}");
+        assert!(verify(&verifying, "data/studios.json", b"{}", &signature).is_err());
+    }
+
+    #[test]
+    fn rejects_another_key() {
+        let (signing, _) = keypair();
+        let (_, other) = keypair();
+        let signature = sign(&signing, "data/clients.json", b"{}");
+        assert!(verify(&other, "data/clients.json", b"{}", &signature).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        let (_, verifying) = keypair();
+        for signature in ["", "abc", "zz", &"00".repeat(SIGNATURE_LENGTH - 1), &"é".repeat(SIGNATURE_LENGTH)] {
+            let error = verify(&verifying, "data/clients.json", b"{}", signature).unwrap_err();
+            assert!(matches!(error.downcast_ref::<SigningError>(), Some(SigningError::MalformedSignature)), "{}", signature);
+        }
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(decode_hex("00ff10"), Some(vec![0, 255, 16]));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("0g"), None);
+    }
+}
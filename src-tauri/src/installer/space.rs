@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::path::Path;
+
+use super::Result;
+
+#[derive(Debug)]
+pub struct NotEnoughSpace {
+    required: u64,
+    available: u64,
+}
+
+impl Display for NotEnoughSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Not enough disk space, {} MB needed but only {} MB free",
+            self.required.div_ceil(1024 * 1024),
+            self.available / (1024 * 1024)
+        )
+    }
+}
+
+impl Error for NotEnoughSpace {}
+
+/* Walks up to the first folder that exists since the target is often not created yet */
+pub fn available<P: AsRef<Path>>(path: P) -> Result<u64> {
+    let mut path = path.as_ref();
+    while !path.exists() {
+        let Some(parent) = path.parent() else {
+            break;
+        };
+        path = parent;
+    }
+
+    Ok(fs2::available_space(path)?)
+}
+
+pub fn ensure<P: AsRef<Path>>(path: P, required: u64) -> Result<()> {
+    let available = available(path)?;
+    if available < required {
+        return Err(NotEnoughSpace { required, available }.into());
+    }
+
+    Ok(())
+}
@@ -1,9 +1,154 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{ self, Display };
 use std::fs;
-use std::path::PathBuf;
+use std::path::{ Component, Path, PathBuf };
 
-use super::{ paths, download_from_repo, download_and_extract, launch_application };
+use serde::{ Deserialize, Serialize };
+use sha2::{ Digest, Sha256 };
+
+use super::{
+    paths,
+    app_settings,
+    places,
+    plugins,
+    download_from_repo,
+    download_and_extract_from,
+    launch_application,
+    LaunchArg,
+    process,
+    logs,
+    compare_versions,
+    LAUNCHER_VERSION,
+};
+use super::lock::InstallLock;
 use super::Result;
+use crate::config;
+
+/* Holds the version folder launches use, only written once that version was verified */
+const CURRENT_FILE: &str = "current";
+
+/* Installs from before studios were versioned get moved into this folder */
+const LEGACY_VERSION: &str = "legacy";
+
+/* New versions are extracted to the version name with this appended and renamed once verified */
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/* Written into each version folder so launching never needs studios.json */
+const INSTALL_INFO_FILE: &str = "studio.json";
+
+/* Probed in order when studios.json doesnt name the exe */
+const EXECUTABLES: [&str; 2] = ["RobloxStudioBeta.exe", "SyntaxStudioBeta.exe"];
+
+#[derive(Debug)]
+pub enum StudioError {
+    Unavailable(String),
+    NotInstalled(String),
+    VerifyFailed(String),
+    LauncherTooOld {
+        year: String,
+        required: String,
+    },
+}
+
+impl Display for StudioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StudioError::Unavailable(year) => write!(f, "Studio {} is not available", year),
+            StudioError::NotInstalled(year) => write!(f, "Studio {} is not installed", year),
+            StudioError::VerifyFailed(year) => write!(f, "Studio {} download is incomplete", year),
+            StudioError::LauncherTooOld { year, required } =>
+                write!(f, "Studio {} needs launcher version {} or newer", year, required),
+        }
+    }
+}
+
+impl Error for StudioError {}
+
+/*
+    Everything but the url is optional so studios.json can be filled in over time.
+    Unknown fields are ignored so older launchers keep reading newer files
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudioInfo {
+    pub url: String,
+    /* Defaults to a hash of the url */
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub display_name: String,
+    /* Bytes of the zip */
+    pub size: Option<u64>,
+    /* Bytes of everything in the zip */
+    pub extracted_size: Option<u64>,
+    pub sha256: Option<String>,
+    /* Defaults to probing the usual names */
+    pub executable: Option<String>,
+    /* Tried in order after url */
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    pub min_launcher_version: Option<String>,
+}
+
+/* studios.json used to map a year straight to a url, those still parse */
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StudioEntry {
+    Url(String),
+    Info(StudioInfo),
+}
+
+/* A url only entry gets a version from its url so a new upload still counts as an update */
+fn url_version(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .take(6)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+impl StudioEntry {
+    fn into_info(self, year: &str) -> StudioInfo {
+        let mut info = match self {
+            StudioEntry::Url(url) =>
+                StudioInfo {
+                    url,
+                    version: String::new(),
+                    display_name: String::new(),
+                    size: None,
+                    extracted_size: None,
+                    sha256: None,
+                    executable: None,
+                    mirrors: vec![],
+                    min_launcher_version: None,
+                },
+            StudioEntry::Info(info) => info,
+        };
+
+        if info.version.is_empty() {
+            info.version = url_version(&info.url);
+        }
+        if info.display_name.is_empty() {
+            info.display_name = format!("Studio {}", year);
+        }
+        info
+    }
+}
+
+impl StudioInfo {
+    pub fn supported(&self) -> bool {
+        self.min_launcher_version
+            .as_deref()
+            .is_none_or(|required| compare_versions(LAUNCHER_VERSION, required).is_ge())
+    }
+
+    fn sources(&self) -> Vec<String> {
+        let mut sources = vec![self.url.clone()];
+        sources.extend(self.mirrors.iter().cloned());
+        sources
+    }
+}
 
 pub fn get_studio_folder<T: AsRef<str>>(year: T) -> Result<PathBuf> {
     let dir = paths::get_studio_folder()?.join(year.as_ref());
@@ -14,35 +159,281 @@ pub fn get_studio_folder<T: AsRef<str>>(year: T) -> Result<PathBuf> {
     return Ok(dir);
 }
 
-pub fn is_installed<T: AsRef<str>>(year: T) -> bool {
-    let Ok(path) = get_studio_folder(year) else {
-        return false;
+fn read_install_info(version_folder: &Path) -> Option<StudioInfo> {
+    let bytes = fs::read(version_folder.join(INSTALL_INFO_FILE)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+/* The name comes from downloaded json, so it has to be a plain relative path that stays inside the version */
+fn is_contained(executable: &str) -> bool {
+    let path = Path::new(executable);
+    path.components().next().is_some() && path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn executable_in(version_folder: &Path, executable: Option<&str>) -> PathBuf {
+    match executable {
+        Some(executable) if is_contained(executable) => {
+            return version_folder.join(executable);
+        }
+        Some(executable) => {
+            tracing::warn!(executable, "ignoring studio executable outside the install");
+        }
+        None => {}
+    }
+
+    /* Older studios still ship under the roblox name */
+    EXECUTABLES.iter()
+        .map(|name| version_folder.join(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| version_folder.join(EXECUTABLES[EXECUTABLES.len() - 1]))
+}
+
+/* Uses the exe name recorded at install time, probing only for installs that predate it */
+pub fn find_executable(version_folder: &Path) -> PathBuf {
+    let info = read_install_info(version_folder);
+    executable_in(version_folder, info.as_ref().and_then(|info| info.executable.as_deref()))
+}
+
+fn verify(version_folder: &Path) -> bool {
+    version_folder.join(app_settings::APP_SETTINGS_FILE).exists() && find_executable(version_folder).exists()
+}
+
+/* An install from before versioning has its files straight in the year folder */
+fn is_legacy(year_folder: &Path) -> bool {
+    !year_folder.join(CURRENT_FILE).exists() && year_folder.join(app_settings::APP_SETTINGS_FILE).exists()
+}
+
+/*
+    Dot files are the install lock, everything else was extracted by the old install once.
+    Takes the lock so nothing can move files out from under a running install
+*/
+fn migrate_legacy(year_folder: &Path, _lock: &InstallLock) -> Result<()> {
+    if !is_legacy(year_folder) {
+        return Ok(());
+    }
+
+    tracing::info!(folder = %year_folder.display(), "moving unversioned studio install");
+    let legacy = year_folder.join(LEGACY_VERSION);
+    fs::create_dir_all(&legacy)?;
+    for entry in fs::read_dir(year_folder)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == LEGACY_VERSION || name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        fs::rename(entry.path(), legacy.join(name))?;
+    }
+
+    set_current(year_folder, LEGACY_VERSION)
+}
+
+fn set_current(year_folder: &Path, version: &str) -> Result<()> {
+    paths::check_name(version)?;
+    let current = year_folder.join(CURRENT_FILE);
+    let temp = current.with_extension("tmp");
+    fs::write(&temp, version)?;
+    fs::rename(temp, current)?;
+    Ok(())
+}
+
+pub fn current_version<T: AsRef<str>>(year: T) -> Result<Option<String>> {
+    let year_folder = paths::get_studio_folder()?.join(year.as_ref());
+    if !year_folder.exists() {
+        return Ok(None);
+    }
+    /* Reads leave moving it to the next install or launch, which hold the lock */
+    if is_legacy(&year_folder) {
+        return Ok(Some(LEGACY_VERSION.to_string()));
+    }
+
+    match fs::read_to_string(year_folder.join(CURRENT_FILE)) {
+        Ok(version) => Ok(Some(paths::check_name(version.trim())?.to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/* The verified version launches use, None when nothing was installed yet */
+pub fn get_install_folder<T: AsRef<str>>(year: T) -> Result<Option<PathBuf>> {
+    let year = year.as_ref();
+    let year_folder = paths::get_studio_folder()?.join(year);
+    if is_legacy(&year_folder) {
+        return Ok(Some(year_folder));
+    }
+    let Some(version) = current_version(year)? else {
+        return Ok(None);
     };
-    return path.join("AppSettings.xml").exists();
+    Ok(Some(year_folder.join(version)))
 }
 
-pub async fn get_available() -> Result<HashMap<String, String>> {
+pub fn is_installed<T: AsRef<str>>(year: T) -> bool {
+    match get_install_folder(year) {
+        Ok(Some(folder)) => verify(&folder),
+        _ => false,
+    }
+}
+
+pub async fn get_available() -> Result<HashMap<String, StudioInfo>> {
     let file = download_from_repo("data/studios.json").await?;
-    let decoded: HashMap<String, String> = serde_json::from_slice(&file)?;
+    let decoded: HashMap<String, StudioEntry> = serde_json::from_slice(&file)?;
 
-    Ok(decoded)
+    Ok(
+        decoded
+            .into_iter()
+            .map(|(year, entry)| {
+                let info = entry.into_info(&year);
+                (year, info)
+            })
+            .collect()
+    )
 }
 
-pub async fn download_studio<V: AsRef<str>, U: AsRef<str>>(year: V, url: U) -> Result<()> {
-    let studio_folder = get_studio_folder(year)?;
-    download_and_extract(url, studio_folder).await?;
+/* A studio that isnt installed is not outdated, it just needs installing */
+pub async fn is_outdated<T: AsRef<str>>(year: T) -> Result<bool> {
+    let year = year.as_ref();
+    let Some(installed) = current_version(year)? else {
+        return Ok(false);
+    };
+    let available = get_available().await?;
+    let Some(info) = available.get(year) else {
+        return Ok(false);
+    };
 
-    Ok(())
+    /* Nothing to update to when this launcher cant install the new build */
+    if !info.supported() {
+        tracing::info!(year, version = info.version, "studio update needs a newer launcher");
+        return Ok(false);
+    }
+
+    Ok(info.version != installed)
 }
 
-pub async fn launch_studio<V: AsRef<str>>(year: V) -> Result<()> {
-    let studio_folder = get_studio_folder(year)?;
-    let mut studio_executeable = studio_folder.join("RobloxStudioBeta.exe");
+/* Newest first, the current version takes up one of the slots */
+fn prune_versions(year_folder: &Path, current: &str) -> Result<()> {
+    paths::check_name(current)?;
+    let keep = config::get().retention.studio_versions;
+
+    let mut versions = vec![];
+    for entry in fs::read_dir(year_folder)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() || entry.file_name() == current {
+            continue;
+        }
+        versions.push((entry.metadata()?.modified()?, entry.path()));
+    }
 
-    if !studio_executeable.exists() {
-        studio_executeable = studio_folder.join("SyntaxStudioBeta.exe");
+    versions.sort_by_key(|(modified, _)| Reverse(*modified));
+    for (_, old) in versions.into_iter().skip(keep.saturating_sub(1)) {
+        /* A studio still open from an older build keeps it until a later update */
+        if process::in_use(&old) {
+            tracing::info!(folder = %old.display(), "skipping running studio version");
+            continue;
+        }
+        tracing::info!(folder = %old.display(), "removing old studio version");
+        fs::remove_dir_all(old)?;
     }
 
-    launch_application(studio_executeable, &[])?;
     Ok(())
 }
+
+/*
+    Installs the available version beside the current one.
+    Launches keep using the old build until the new one is extracted and verified
+*/
+pub async fn download_studio<V: AsRef<str>>(year: V) -> Result<()> {
+    let year = year.as_ref();
+    let available = get_available().await?;
+    let Some(info) = available.get(year) else {
+        return Err(StudioError::Unavailable(year.to_string()).into());
+    };
+    if let (false, Some(required)) = (info.supported(), &info.min_launcher_version) {
+        return Err(StudioError::LauncherTooOld { year: year.to_string(), required: required.clone() }.into());
+    }
+
+    let year_folder = get_studio_folder(year)?;
+    let lock = InstallLock::acquire(&year_folder)?;
+    migrate_legacy(&year_folder, &lock)?;
+
+    let version = paths::check_name(&info.version)?;
+    let target = year_folder.join(version);
+    if verify(&target) {
+        set_current(&year_folder, version)?;
+        return prune_versions(&year_folder, version);
+    }
+
+    /* Appended rather than with_extension, which would swap out the last part of a dotted version */
+    let partial = year_folder.join(format!("{}{}", version, PARTIAL_SUFFIX));
+    if partial.exists() {
+        fs::remove_dir_all(&partial)?;
+    }
+
+    tracing::info!(year, version = info.version, "installing studio version");
+    let installed = download_and_extract_from(
+        &info.sources(),
+        info.sha256.as_deref(),
+        info.size,
+        info.extracted_size,
+        &partial
+    ).await.and_then(|_| {
+        fs::write(partial.join(INSTALL_INFO_FILE), serde_json::to_vec_pretty(info)?)?;
+        /* Before apply, which would write a missing AppSettings.xml and hide a broken zip */
+        if !verify(&partial) {
+            return Err(StudioError::VerifyFailed(year.to_string()).into());
+        }
+        app_settings::apply(&partial)?;
+        if target.exists() {
+            fs::remove_dir_all(&target)?;
+        }
+        fs::rename(&partial, &target)?;
+        Ok(())
+    });
+
+    if let Err(e) = installed {
+        let _ = fs::remove_dir_all(&partial);
+        return Err(e);
+    }
+
+    set_current(&year_folder, version)?;
+    prune_versions(&year_folder, version)
+}
+
+/* A place file is handed to studio to open and remembered as opened with this year */
+pub async fn launch_studio<V: AsRef<str>>(
+    year: V,
+    file: Option<&str>,
+    crash_window: Option<u64>
+) -> Result<process::LaunchReport> {
+    let year = year.as_ref();
+    let year_folder = paths::get_studio_folder()?.join(year);
+    if is_legacy(&year_folder) {
+        migrate_legacy(&year_folder, &InstallLock::acquire(&year_folder)?)?;
+    }
+    let Some(install_folder) = get_install_folder(year)? else {
+        return Err(StudioError::NotInstalled(year.to_string()).into());
+    };
+    let studio_executeable = find_executable(&install_folder);
+    app_settings::apply(&install_folder)?;
+
+    /* Studio still opens without them, a plugin that didnt copy is only logged */
+    if let Err(e) = plugins::sync_plugins(year) {
+        tracing::warn!(year, error = %e, "couldnt sync studio plugins");
+    }
+
+    let place = file.map(places::place_path).transpose()?;
+    let mut args = vec![];
+    if let Some(place) = &place {
+        places::remember_year(place, year)?;
+        args.push(LaunchArg::Path(place));
+    }
+
+    let log = logs::create_launch_log(
+        &(logs::LaunchTag {
+            kind: "studio",
+            year,
+            version: current_version(year)?.as_deref(),
+            mode: "edit",
+        })
+    )?;
+    let child = launch_application(studio_executeable, &args, log)?;
+    process::report(child, crash_window).await
+}
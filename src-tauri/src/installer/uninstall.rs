@@ -0,0 +1,162 @@
+use std::error::Error;
+use std::fmt::{ self, Display };
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{ Path, PathBuf };
+
+use super::{ paths, places, process, self_update, uri, Result };
+use super::lock::{ self, InstallLock };
+use crate::{ config, instance };
+
+#[derive(Debug)]
+pub struct InstallRunning {
+    path: PathBuf,
+}
+
+impl Display for InstallRunning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is still running, close it before uninstalling", self.path.display())
+    }
+}
+
+impl Error for InstallRunning {}
+
+/* Waits for nothing, an install in progress or a running client makes the uninstall fail instead */
+fn remove_install<P: AsRef<Path>>(dir: P) -> Result<()> {
+    let dir = dir.as_ref();
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let lock = InstallLock::acquire(dir)?;
+    if process::in_use(dir) {
+        return Err(InstallRunning { path: dir.to_path_buf() }.into());
+    }
+
+    tracing::info!(folder = %dir.display(), "uninstalling");
+    lock::remove_locked(dir, lock)
+}
+
+/* Without a version every version of the year goes */
+pub fn uninstall_client<T: AsRef<str>>(year: T, version: Option<&str>) -> Result<()> {
+    let year_folder = paths::get_clients_folder()?.join(paths::check_name(year.as_ref())?);
+
+    match version {
+        Some(version) => remove_install(year_folder.join(paths::check_name(version)?)),
+        None => {
+            if !year_folder.exists() {
+                return Ok(());
+            }
+            for entry in fs::read_dir(&year_folder)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    remove_install(entry.path())?;
+                }
+            }
+            fs::remove_dir_all(year_folder)?;
+            Ok(())
+        }
+    }
+}
+
+pub async fn uninstall_studio<T: AsRef<str>>(year: T) -> Result<()> {
+    let year = paths::check_name(year.as_ref())?;
+    remove_install(paths::get_studio_folder()?.join(year))?;
+    uri::remove_studio_shortcut(year).await
+}
+
+fn remove_folder_if_exists<P: AsRef<Path>>(dir: P) -> Result<()> {
+    match fs::remove_dir_all(dir) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/*
+    Removes every install and undoes everything installer::uri registered.
+    Logs and the config are kept until the end since the launcher is still using them
+*/
+pub async fn uninstall_all() -> Result<()> {
+    uri::unregister_uri().await?;
+    uri::unregister_place_files().await?;
+    uri::remove_all_shortcuts().await?;
+
+    let clients = paths::get_clients_folder()?;
+    for year in fs::read_dir(&clients)? {
+        let year = year?;
+        if year.file_type()?.is_dir() {
+            uninstall_client(year.file_name().to_string_lossy(), None)?;
+        }
+    }
+
+    let studios = paths::get_studio_folder()?;
+    for year in fs::read_dir(&studios)? {
+        let year = year?;
+        if year.file_type()?.is_dir() {
+            remove_install(year.path())?;
+        }
+    }
+
+    remove_folder_if_exists(clients)?;
+    remove_folder_if_exists(studios)?;
+    remove_folder_if_exists(paths::get_downloads_folder()?)?;
+    remove_folder_if_exists(paths::get_plugins_folder()?)?;
+    remove_folder_if_exists(paths::get_content_packs_folder()?)?;
+
+    /* The open launcher log cant be removed on windows, the rest of the folder can */
+    if let Err(e) = remove_folder_if_exists(paths::get_logs_folder()?) {
+        tracing::warn!(error = %e, "couldnt remove every log");
+    }
+
+    remove_file_if_exists(places::place_years_path()?)?;
+    for file in uri::state_files()? {
+        remove_file_if_exists(file)?;
+    }
+
+    let config_folder = config::config_folder()?;
+    remove_file_if_exists(config_folder.join(self_update::VERSION_MARKER))?;
+    remove_file_if_exists(config_folder.join(instance::PORT_FILE))?;
+    /* Windows wont delete the lock while this launcher holds it, it is empty so leaving it is harmless */
+    if let Err(e) = remove_file_if_exists(config_folder.join(instance::LOCK_FILE)) {
+        tracing::warn!(error = %e, "couldnt remove the launcher lock");
+    }
+
+    remove_file_if_exists(config::config_path()?)
+}
+
+fn remove_file_if_exists<P: AsRef<Path>>(file: P) -> Result<()> {
+    match fs::remove_file(file) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn install_in_use_is_kept() {
+        let root = tempfile::tempdir().unwrap();
+        let folder = root.path().join("version-1");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("RobloxPlayerBeta.exe"), "exe").unwrap();
+
+        let lock = InstallLock::acquire(&folder).unwrap();
+        assert!(remove_install(&folder).is_err());
+        drop(lock);
+
+        let mut child = Command::new("sleep").arg("30").current_dir(&folder).spawn().unwrap();
+        assert!(remove_install(&folder).is_err());
+        assert!(folder.join("RobloxPlayerBeta.exe").exists());
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        remove_install(&folder).unwrap();
+        assert!(!folder.exists());
+    }
+}
@@ -0,0 +1,605 @@
+use std::{ error::Error, fmt::{ self, Display } };
+
+/*
+    Reader and writer for freedesktop Desktop Entry files.
+    They look like ini but have their own escaping rules, localized keys and typed lists
+    https://specifications.freedesktop.org/desktop-entry-spec/latest/
+*/
+
+pub const MAIN_GROUP: &str = "Desktop Entry";
+const ACTION_GROUP_PREFIX: &str = "Desktop Action ";
+
+/* Characters that force an Exec argument to be quoted */
+const EXEC_RESERVED: [char; 19] = [
+    ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`',
+];
+
+#[derive(Debug)]
+pub enum ParseError {
+    OutsideGroup(usize),
+    Malformed(usize),
+    InvalidKey(usize),
+    DuplicateGroup(String),
+    DuplicateKey(usize),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::OutsideGroup(line) => write!(f, "Line {} is not inside a group", line),
+            ParseError::Malformed(line) => write!(f, "Line {} is not a group, key or comment", line),
+            ParseError::InvalidKey(line) => write!(f, "Line {} has an invalid key", line),
+            ParseError::DuplicateGroup(name) => write!(f, "Group {} appears more than once", name),
+            ParseError::DuplicateKey(line) => write!(f, "Line {} repeats a key", line),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/* Comments are kept with the raw text after the # so a rewrite leaves them where they were */
+#[derive(Debug, Clone, PartialEq)]
+enum Line {
+    Comment(String),
+    /* Localized keys are stored with their locale suffix, eg Name[de], so they survive a round trip */
+    Entry(String, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    name: String,
+    /* Comment lines directly above the group header */
+    comments: Vec<String>,
+    lines: Vec<Line>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DesktopEntry {
+    groups: Vec<Group>,
+    /* Only used for comments in a file without any group */
+    comments: Vec<String>,
+}
+
+fn escape_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            /* Leading whitespace would be eaten by the parser otherwise */
+            ' ' if i == 0 => escaped.push_str("\\s"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/* Unknown escapes are kept as they are, list separators are left for split_list */
+fn unescape_string(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut current = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' =>
+                match chars.next() {
+                    Some(';') => current.push(';'),
+                    Some(other) => {
+                        current.push('\\');
+                        current.push(other);
+                    }
+                    None => current.push('\\'),
+                }
+            ';' => items.push(unescape_string(&std::mem::take(&mut current))),
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        items.push(unescape_string(&current));
+    }
+    items
+}
+
+fn valid_key(key: &str) -> bool {
+    let (name, locale) = match key.split_once('[') {
+        Some((name, rest)) => {
+            let Some(locale) = rest.strip_suffix(']') else {
+                return false;
+            };
+            (name, Some(locale))
+        }
+        None => (key, None),
+    };
+
+    let name_ok = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    let locale_ok = locale.is_none_or(|locale| {
+        !locale.is_empty() && locale.chars().all(|c| c.is_ascii_alphanumeric() || "_@.-".contains(c))
+    });
+    name_ok && locale_ok
+}
+
+/* Quotes a single Exec argument, the result still goes through string escaping when written */
+fn quote_exec_arg(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    if !arg.is_empty() && !arg.contains(EXEC_RESERVED) {
+        return arg;
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/* Field codes like %u are appended unquoted so the launcher expands them */
+pub fn build_exec<S: AsRef<str>>(program: &str, arguments: &[S], field_code: Option<&str>) -> String {
+    let mut exec = quote_exec_arg(program);
+    for argument in arguments {
+        exec.push(' ');
+        exec.push_str(&quote_exec_arg(argument.as_ref()));
+    }
+    if let Some(code) = field_code {
+        exec.push(' ');
+        exec.push_str(code);
+    }
+    exec
+}
+
+/* Reverses build_exec, field codes are returned as their own arguments */
+pub fn split_exec(exec: &str) -> Vec<String> {
+    let mut arguments = vec![];
+    let mut current = String::new();
+    let mut in_argument = false;
+    let mut quoted = false;
+    let mut chars = exec.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_argument = true;
+            }
+            '\\' if quoted => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                current.push('%');
+                in_argument = true;
+            }
+            ' ' | '\t' if !quoted => {
+                if in_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    in_argument = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_argument = true;
+            }
+        }
+    }
+    if in_argument {
+        arguments.push(current);
+    }
+    arguments
+}
+
+/*
+    Locales are lang_COUNTRY.ENCODING@MODIFIER, the encoding never takes part in matching.
+    Returned most specific first, the order the spec says to look keys up in
+*/
+fn locale_candidates(locale: &str) -> Vec<String> {
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((rest, modifier)) => (rest, Some(modifier)),
+        None => (locale, None),
+    };
+    let rest = rest.split_once('.').map_or(rest, |(rest, _)| rest);
+    let (lang, country) = match rest.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (rest, None),
+    };
+
+    let mut candidates = vec![];
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        candidates.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        candidates.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        candidates.push(format!("{}@{}", lang, modifier));
+    }
+    if !lang.is_empty() {
+        candidates.push(lang.to_string());
+    }
+    candidates
+}
+
+/* The locale messages are shown in, same lookup order as gettext. C and POSIX mean no locale */
+pub fn system_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| value != "C" && value != "POSIX")
+}
+
+impl Group {
+    pub fn new<N: Into<String>>(name: N) -> Self {
+        Group { name: name.into(), comments: vec![], lines: vec![] }
+    }
+
+    fn set_raw(&mut self, key: String, raw: String) {
+        let existing = self.lines.iter_mut().find_map(|line| match line {
+            Line::Entry(existing, value) if *existing == key => Some(value),
+            _ => None,
+        });
+        match existing {
+            Some(value) => {
+                *value = raw;
+            }
+            None => self.lines.push(Line::Entry(key, raw)),
+        }
+    }
+
+    fn get_raw(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Entry(existing, value) if existing == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn set<K: Into<String>>(&mut self, key: K, value: &str) -> &mut Self {
+        self.set_raw(key.into(), escape_string(value));
+        self
+    }
+
+    /* Every item is terminated with a semicolon, which is what most desktop files do */
+    pub fn set_list<S: AsRef<str>>(&mut self, key: &str, items: &[S]) -> &mut Self {
+        let raw: String = items
+            .iter()
+            .map(|item| format!("{};", escape_string(item.as_ref()).replace(';', "\\;")))
+            .collect();
+        self.set_raw(key.into(), raw);
+        self
+    }
+
+    pub fn set_bool(&mut self, key: &str, value: bool) -> &mut Self {
+        self.set_raw(key.into(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.get_raw(key).map(unescape_string)
+    }
+
+    pub fn get_list(&self, key: &str) -> Option<Vec<String>> {
+        self.get_raw(key).map(split_list)
+    }
+
+    /* Falls back from the most specific translation to the untranslated key */
+    pub fn get_localized(&self, key: &str, locale: Option<&str>) -> Option<String> {
+        locale
+            .map(locale_candidates)
+            .unwrap_or_default()
+            .iter()
+            .find_map(|candidate| self.get(&format!("{}[{}]", key, candidate)))
+            .or_else(|| self.get(key))
+    }
+}
+
+impl DesktopEntry {
+    pub fn new() -> Self {
+        DesktopEntry::default()
+    }
+
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    /* Creates the group at the end when it doesnt exist yet */
+    pub fn group_mut(&mut self, name: &str) -> &mut Group {
+        let index = match self.groups.iter().position(|group| group.name == name) {
+            Some(index) => index,
+            None => {
+                self.groups.push(Group::new(name));
+                self.groups.len() - 1
+            }
+        };
+        &mut self.groups[index]
+    }
+
+    pub fn main(&self) -> Option<&Group> {
+        self.group(MAIN_GROUP)
+    }
+
+    pub fn main_mut(&mut self) -> &mut Group {
+        self.group_mut(MAIN_GROUP)
+    }
+
+    /* Adds a [Desktop Action id] group and lists it under Actions in the main group */
+    pub fn add_action(&mut self, id: &str) -> &mut Group {
+        let mut actions = self.actions();
+        if !actions.iter().any(|action| action == id) {
+            actions.push(id.to_string());
+        }
+        self.main_mut().set_list("Actions", &actions);
+
+        self.group_mut(&format!("{}{}", ACTION_GROUP_PREFIX, id))
+    }
+
+    pub fn actions(&self) -> Vec<String> {
+        self.main()
+            .and_then(|main| main.get_list("Actions"))
+            .unwrap_or_default()
+    }
+
+    pub fn action(&self, id: &str) -> Option<&Group> {
+        self.group(&format!("{}{}", ACTION_GROUP_PREFIX, id))
+    }
+
+    /*
+        Blank lines are dropped, everything else survives a round trip.
+        Comments belong to the group header or key that follows them
+    */
+    pub fn parse(content: &str) -> Result<Self, ParseError> {
+        let mut entry = DesktopEntry::new();
+        let mut current: Option<usize> = None;
+        let mut comments = vec![];
+
+        for (index, line) in content.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim_start();
+            if line.is_empty() {
+                /* A blank line ends the comments of the group above, only ones touching a header belong to it */
+                if let Some(group) = current {
+                    entry.groups[group].lines.extend(comments.drain(..).map(Line::Comment));
+                }
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                comments.push(comment.trim_end().to_string());
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[') {
+                let Some(name) = name.trim_end().strip_suffix(']') else {
+                    return Err(ParseError::Malformed(number));
+                };
+                if entry.group(name).is_some() {
+                    return Err(ParseError::DuplicateGroup(name.to_string()));
+                }
+
+                let mut group = Group::new(name);
+                group.comments = std::mem::take(&mut comments);
+                entry.groups.push(group);
+                current = Some(entry.groups.len() - 1);
+                continue;
+            }
+
+            let Some(group) = current else {
+                return Err(ParseError::OutsideGroup(number));
+            };
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(ParseError::Malformed(number));
+            };
+
+            let key = key.trim_end();
+            if !valid_key(key) {
+                return Err(ParseError::InvalidKey(number));
+            }
+
+            let group = &mut entry.groups[group];
+            if group.get_raw(key).is_some() {
+                return Err(ParseError::DuplicateKey(number));
+            }
+            group.lines.extend(comments.drain(..).map(Line::Comment));
+            group.lines.push(Line::Entry(key.to_string(), value.trim_start().to_string()));
+        }
+
+        /* Trailing comments stay at the end of the last group */
+        match entry.groups.last_mut() {
+            Some(group) => group.lines.extend(comments.into_iter().map(Line::Comment)),
+            None => {
+                entry.comments = comments;
+            }
+        }
+        Ok(entry)
+    }
+}
+
+impl Display for DesktopEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for comment in &self.comments {
+            writeln!(f, "#{}", comment)?;
+        }
+        for (index, group) in self.groups.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            for comment in &group.comments {
+                writeln!(f, "#{}", comment)?;
+            }
+            writeln!(f, "[{}]", group.name)?;
+            for line in &group.lines {
+                match line {
+                    Line::Comment(comment) => writeln!(f, "#{}", comment)?,
+                    Line::Entry(key, value) => writeln!(f, "{}={}", key, value)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# Written by the launcher
+[Desktop Entry]
+Type=Application
+Name=SYNTAX
+Name[de]=SYNTAX Starter
+Name[de_AT]=SYNTAX Oida
+# Keep the field code last
+Exec=/opt/syntax/launcher %u
+Actions=studio-2021;repair;
+
+# Actions are listed in the order of Actions
+[Desktop Action studio-2021]
+Name=Studio 2021
+Exec=/opt/syntax/launcher --studio 2021
+
+[Desktop Action repair]
+Name=Repair installs
+Name[de]=Installationen reparieren
+Exec=/opt/syntax/launcher --repair
+# trailing
+";
+
+    #[test]
+    fn round_trip_keeps_comments_and_group_order() {
+        let entry = DesktopEntry::parse(SAMPLE).unwrap();
+        let written = entry.to_string();
+        assert_eq!(written, SAMPLE);
+
+        let reparsed = DesktopEntry::parse(&written).unwrap();
+        assert_eq!(reparsed, entry);
+
+        let names: Vec<&str> = reparsed.groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(names, [MAIN_GROUP, "Desktop Action studio-2021", "Desktop Action repair"]);
+    }
+
+    #[test]
+    fn comments_without_groups_survive() {
+        let content = "# nothing here\n#\n";
+        let entry = DesktopEntry::parse(content).unwrap();
+        assert_eq!(entry.to_string(), content);
+    }
+
+    #[test]
+    fn set_keeps_comment_position() {
+        let mut entry = DesktopEntry::parse(SAMPLE).unwrap();
+        entry.main_mut().set("Exec", "/usr/bin/syntax %u");
+
+        let written = entry.to_string();
+        assert!(written.contains("# Keep the field code last\nExec=/usr/bin/syntax %u\n"));
+    }
+
+    #[test]
+    fn exec_round_trips_awkward_arguments() {
+        let arguments = ["with space", "\"quoted\"", "back\\slash", "$HOME", "100%", "'single'", ""];
+        let exec = build_exec("/opt/my launcher/syntax", &arguments, Some("%u"));
+
+        let mut expected = vec!["/opt/my launcher/syntax".to_string()];
+        expected.extend(arguments.iter().map(|argument| argument.to_string()));
+        expected.push("%u".to_string());
+        assert_eq!(split_exec(&exec), expected);
+
+        /* The string escaping of the file goes on top of the exec quoting */
+        let mut entry = DesktopEntry::new();
+        entry.main_mut().set("Exec", &exec);
+        let reparsed = DesktopEntry::parse(&entry.to_string()).unwrap();
+        assert_eq!(split_exec(&reparsed.main().unwrap().get("Exec").unwrap()), expected);
+    }
+
+    #[test]
+    fn exec_quoting() {
+        let exec = build_exec("/usr/bin/syntax", &["a b", "a\"b", "c\\d", "$x", "50%"], None);
+        assert_eq!(exec, r#"/usr/bin/syntax "a b" "a\"b" "c\\d" "\$x" 50%%"#);
+
+        let mut entry = DesktopEntry::new();
+        entry.main_mut().set("Exec", &exec);
+        assert!(entry.to_string().contains(r#"Exec=/usr/bin/syntax "a b" "a\\"b" "c\\\\d" "\\$x" 50%%"#));
+    }
+
+    #[test]
+    fn localized_lookup() {
+        let entry = DesktopEntry::parse(SAMPLE).unwrap();
+        let main = entry.main().unwrap();
+
+        assert_eq!(main.get_localized("Name", None).as_deref(), Some("SYNTAX"));
+        assert_eq!(main.get_localized("Name", Some("fr_FR.UTF-8")).as_deref(), Some("SYNTAX"));
+        assert_eq!(main.get_localized("Name", Some("de_DE.UTF-8")).as_deref(), Some("SYNTAX Starter"));
+        assert_eq!(main.get_localized("Name", Some("de_AT.UTF-8@euro")).as_deref(), Some("SYNTAX Oida"));
+        assert_eq!(main.get_localized("Name", Some("de@euro")).as_deref(), Some("SYNTAX Starter"));
+        assert_eq!(main.get("Name[de]").as_deref(), Some("SYNTAX Starter"));
+    }
+
+    #[test]
+    fn locale_candidate_order() {
+        assert_eq!(locale_candidates("sr_YU.UTF-8@Latn"), ["sr_YU@Latn", "sr_YU", "sr@Latn", "sr"]);
+        assert_eq!(locale_candidates("de"), ["de"]);
+    }
+
+    #[test]
+    fn actions() {
+        let mut entry = DesktopEntry::parse(SAMPLE).unwrap();
+        assert_eq!(entry.actions(), ["studio-2021", "repair"]);
+
+        let repair = entry.action("repair").unwrap();
+        assert_eq!(repair.get_localized("Name", Some("de_DE")).as_deref(), Some("Installationen reparieren"));
+
+        entry.add_action("repair").set("Exec", "/usr/bin/syntax --repair");
+        entry.add_action("logs").set("Name", "Open logs");
+        assert_eq!(entry.actions(), ["studio-2021", "repair", "logs"]);
+        assert_eq!(entry.action("repair").unwrap().get("Exec").as_deref(), Some("/usr/bin/syntax --repair"));
+
+        let reparsed = DesktopEntry::parse(&entry.to_string()).unwrap();
+        assert_eq!(reparsed, entry);
+    }
+
+    #[test]
+    fn lists_escape_separators() {
+        let mut entry = DesktopEntry::new();
+        entry.main_mut().set_list("Keywords", &["a;b", "c"]);
+        let reparsed = DesktopEntry::parse(&entry.to_string()).unwrap();
+        assert_eq!(reparsed.main().unwrap().get_list("Keywords").unwrap(), ["a;b", "c"]);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(matches!(DesktopEntry::parse("Name=x\n"), Err(ParseError::OutsideGroup(1))));
+        assert!(matches!(DesktopEntry::parse("[A]\n[A]\n"), Err(ParseError::DuplicateGroup(_))));
+        assert!(matches!(DesktopEntry::parse("[A]\nName=x\nName=y\n"), Err(ParseError::DuplicateKey(3))));
+        assert!(matches!(DesktopEntry::parse("[A]\nNa me=x\n"), Err(ParseError::InvalidKey(2))));
+    }
+}
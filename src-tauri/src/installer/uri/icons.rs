@@ -0,0 +1,196 @@
+use std::{ error::Error, fmt::Display, io::ErrorKind, path::{ Path, PathBuf }, process::Command };
+
+use pelite::PeFile;
+use tokio::fs;
+
+use crate::installer::studio;
+
+/*
+    Desktop environments only render icons looked up by name from an icon theme,
+    so the launcher installs its pngs into the users hicolor theme instead of pointing at the binary
+*/
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+pub const LAUNCHER_ICON: &str = "syntax-launcher";
+const STUDIO_ICON_PREFIX: &str = "syntax-studio-";
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/* Folders hicolor ships with, images of any other size would never be looked at */
+const HICOLOR_SIZES: [u32; 13] = [16, 22, 24, 32, 36, 48, 64, 72, 96, 128, 192, 256, 512];
+
+const LAUNCHER_PNGS: [(u32, &[u8]); 6] = [
+    (256, include_bytes!("../../../icons/Bootstrapper-1.png")),
+    (128, include_bytes!("../../../icons/Bootstrapper-2.png")),
+    (64, include_bytes!("../../../icons/Bootstrapper-3.png")),
+    (48, include_bytes!("../../../icons/Bootstrapper-4.png")),
+    (32, include_bytes!("../../../icons/Bootstrapper-5.png")),
+    (16, include_bytes!("../../../icons/Bootstrapper-6.png")),
+];
+
+#[derive(Debug)]
+pub struct CouldntGetIconFolder;
+
+impl Display for CouldntGetIconFolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Couldnt locate the icon theme folder")
+    }
+}
+
+impl Error for CouldntGetIconFolder {}
+
+fn hicolor_folder() -> Result<PathBuf> {
+    let Some(data_dir) = dirs::data_local_dir() else {
+        return Err(CouldntGetIconFolder.into());
+    };
+    Ok(data_dir.join("icons").join("hicolor"))
+}
+
+fn icon_path(hicolor: &Path, size: u32, name: &str) -> PathBuf {
+    hicolor.join(format!("{}x{}", size, size)).join("apps").join(format!("{}.png", name))
+}
+
+/* Returns true when the file was missing or held something else */
+async fn write_if_changed(path: &Path, bytes: &[u8]) -> Result<bool> {
+    if fs::read(path).await.is_ok_and(|existing| existing == bytes) {
+        return Ok(false);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, bytes).await?;
+    Ok(true)
+}
+
+async fn install_images(name: &str, images: &[(u32, Vec<u8>)]) -> Result<bool> {
+    let hicolor = hicolor_folder()?;
+    let mut changed = false;
+    for (size, png) in images {
+        changed |= write_if_changed(&icon_path(&hicolor, *size, name), png).await?;
+    }
+
+    Ok(changed)
+}
+
+async fn remove_images(name: &str) -> Result<()> {
+    let hicolor = hicolor_folder()?;
+    for size in HICOLOR_SIZES {
+        match fs::remove_file(icon_path(&hicolor, size, name)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn install_launcher_icon() -> Result<bool> {
+    let images: Vec<(u32, Vec<u8>)> = LAUNCHER_PNGS
+        .iter()
+        .map(|(size, png)| (*size, png.to_vec()))
+        .collect();
+    install_images(LAUNCHER_ICON, &images).await
+}
+
+/*
+    Pulls the png images out of the first icon group in the exe.
+    Older icons are stored as bitmaps which we cant hand to the theme so those are skipped
+*/
+fn extract_pe_icons(exe: &Path) -> Result<Vec<(u32, Vec<u8>)>> {
+    let bytes = std::fs::read(exe)?;
+    let file = PeFile::from_bytes(&bytes)?;
+    let resources = file.resources()?;
+
+    let Some((_, group)) = resources.icons().find_map(|icon| icon.ok()) else {
+        return Ok(vec![]);
+    };
+
+    let mut images = vec![];
+    for entry in group.entries() {
+        /* A width of 0 means 256 */
+        let size = if entry.bWidth == 0 { 256 } else { entry.bWidth as u32 };
+        if !HICOLOR_SIZES.contains(&size) || images.iter().any(|(existing, _)| *existing == size) {
+            continue;
+        }
+
+        let Ok(image) = group.image(entry.nId) else {
+            continue;
+        };
+        if image.starts_with(PNG_SIGNATURE) {
+            images.push((size, image.to_vec()));
+        }
+    }
+
+    Ok(images)
+}
+
+/*
+    Installs the icon baked into that years studio and returns the name to put in its desktop entry.
+    Falls back to the launcher icon when the studio isnt installed or has no usable images
+*/
+pub async fn install_studio_icon(year: &str) -> Result<(String, bool)> {
+    let exe = studio::get_install_folder(year)?.map(|folder| studio::find_executable(&folder));
+    let images = if let Some(exe) = exe.filter(|exe| exe.exists()) {
+        extract_pe_icons(&exe).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, year, "couldnt read icons from studio");
+            vec![]
+        })
+    } else {
+        vec![]
+    };
+
+    if images.is_empty() {
+        return Ok((LAUNCHER_ICON.to_string(), install_launcher_icon().await?));
+    }
+
+    let name = format!("{}{}", STUDIO_ICON_PREFIX, year);
+    let changed = install_images(&name, &images).await?;
+    Ok((name, changed))
+}
+
+pub async fn remove_studio_icon(year: &str) -> Result<()> {
+    remove_images(&format!("{}{}", STUDIO_ICON_PREFIX, year)).await
+}
+
+/* Studio icons are found by prefix since the years they were made for might not be known anymore */
+pub async fn remove_all_icons() -> Result<()> {
+    let hicolor = hicolor_folder()?;
+    for size in HICOLOR_SIZES {
+        let apps = hicolor.join(format!("{}x{}", size, size)).join("apps");
+        let Ok(mut entries) = fs::read_dir(apps).await else {
+            continue;
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let ours = name.to_str().is_some_and(|name| {
+                name == format!("{}.png", LAUNCHER_ICON) || (name.starts_with(STUDIO_ICON_PREFIX) && name.ends_with(".png"))
+            });
+
+            if ours {
+                fs::remove_file(entry.path()).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/* Like update-desktop-database this is missing on plenty of systems so failing is only logged */
+pub fn refresh_icon_cache() {
+    let Ok(hicolor) = hicolor_folder() else {
+        return;
+    };
+    match Command::new("gtk-update-icon-cache").arg("-f").arg("-t").arg(hicolor).status() {
+        Ok(status) if !status.success() => {
+            tracing::warn!(code = ?status.code(), "gtk-update-icon-cache failed");
+        }
+        Err(e) => tracing::warn!(error = %e, "couldnt run gtk-update-icon-cache"),
+        _ => {}
+    }
+}
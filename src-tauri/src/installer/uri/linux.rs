@@ -1,48 +1,20 @@
-use std::{ error::Error, env, fmt::Display, collections::HashMap, path::PathBuf, process::Command };
-use serde::{ Serialize, Deserialize };
+use std::{ error::Error, env, fmt::Display, path::PathBuf, process::Command };
+use std::io::ErrorKind;
 use tokio::fs;
 
+use crate::config;
+use crate::installer::{ paths, places::PLACE_EXTENSIONS };
+use super::{ OPEN_PLACE_FLAG, STUDIO_FLAG };
+use super::desktop_entry::{ self, DesktopEntry };
+use super::icons;
+
 /*
-    The .desktop files arent actually ini they are there own thing but ini seems to work just fine
-    Also the files are now compiled from structs instead of using the format! macro
+    The .desktop files arent actually ini they are there own thing,
+    so they are written and read with our own desktop entry module instead of an ini crate
 */
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
-#[derive(Serialize, Deserialize)]
-pub struct Entry {
-    #[serde(rename = "Desktop Entry")]
-    desktop: Desktop,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct Desktop {
-    #[serde(rename = "Name")]
-    name: String,
-    #[serde(rename = "Exec")]
-    exec: String,
-    #[serde(rename = "Terminal")]
-    terminal: String,
-    #[serde(rename = "Type")]
-    app_type: String,
-    #[serde(rename = "MimeType")]
-    mime_type: String,
-    #[serde(rename = "Icon")]
-    icon: String,
-    #[serde(rename = "StartupWMClass")]
-    startup_wm_class: String,
-    #[serde(rename = "Categories")]
-    categories: String,
-    #[serde(rename = "Comment")]
-    comment: String,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct Mimetypes {
-    #[serde(rename = "Default Applications")]
-    default_apps: HashMap<String, String>,
-}
-
 #[derive(Debug)]
 pub struct CouldntLocateExe;
 
@@ -76,77 +48,562 @@ impl Display for CouldntFindDefault {
 
 impl Error for CouldntFindDefault {}
 
-pub fn generate_desktop_str(arguments: &[&str]) -> Result<String> {
+fn launcher_exec(arguments: &[&str], field_code: Option<&str>) -> Result<String> {
     let exe_path = env::current_exe()?;
     let Some(location) = exe_path.to_str() else {
         return Err(CouldntLocateExe.into());
     };
 
-    let desktop = Entry {
-        desktop: Desktop {
-            name: "SYNTAX".into(),
-            exec: format!("{} {} %u", location, arguments.join(" ")),
-            terminal: "false".into(),
-            app_type: "Application".into(),
-            mime_type: "x-scheme-handler/syntax-player;".into(),
-            icon: format!("{}", location),
-            startup_wm_class: "SyntaxLauncher".into(),
-            categories: "Game;".into(),
-            comment: "Syntax Launcher".into(),
-        },
-    };
+    let mut exec_arguments = paths::portable_args();
+    exec_arguments.extend_from_slice(arguments);
 
-    Ok(serde_ini::to_string(&desktop)?)
+    Ok(desktop_entry::build_exec(location, &exec_arguments, field_code))
 }
 
-pub fn generate_mimetypes_str() -> Result<String> {
-    let mut values: HashMap<String, String> = HashMap::new();
-    values.insert("x-scheme-handler/syntax-player".into(), "syntax-player.desktop".into());
+fn generate_desktop_entry(arguments: &[&str], icon: &str) -> Result<DesktopEntry> {
+    let mut desktop = DesktopEntry::new();
+    desktop
+        .main_mut()
+        .set("Type", "Application")
+        .set("Name", "SYNTAX")
+        .set("Comment", "Syntax Launcher")
+        .set("Exec", &launcher_exec(arguments, Some("%u"))?)
+        .set_bool("Terminal", false)
+        .set_list("MimeType", &["x-scheme-handler/syntax-player"])
+        .set("Icon", icon)
+        .set("StartupWMClass", "SyntaxLauncher")
+        .set_list("Categories", &["Game"]);
 
-    Ok(serde_ini::to_string(&values)?)
+    Ok(desktop)
 }
 
-async fn generate_uri<P: AsRef<str>, U: AsRef<str>>(desktop_file_name: P, uri: U) -> Result<()> {
-    let uri = uri.as_ref();
-    let name = desktop_file_name.as_ref();
-    let generated_uri: String = format!("x-scheme-handler/{}", uri);
+/*
+    The launcher entry carries a Desktop Action per studio year plus repair and logs actions.
+    Returns the entry and whether any icon had to be written
+*/
+async fn generate_launcher_entry(years: &[String]) -> Result<(DesktopEntry, bool)> {
+    let mut entry = generate_desktop_entry(&[], icons::LAUNCHER_ICON)?;
+    let mut icons_changed = icons::install_launcher_icon().await?;
+
+    for year in years {
+        let (icon, icon_changed) = icons::install_studio_icon(year).await?;
+        icons_changed |= icon_changed;
+
+        let exec = launcher_exec(&[STUDIO_FLAG, year], None)?;
+        entry
+            .add_action(&format!("{}{}", STUDIO_ACTION_PREFIX, year))
+            .set("Name", &format!("Studio {}", year))
+            .set("Exec", &exec)
+            .set("Icon", &icon);
+    }
+
+    let repair = launcher_exec(&[REPAIR_FLAG], None)?;
+    entry.add_action(REPAIR_ACTION).set("Name", "Repair installs").set("Exec", &repair);
+    let logs = launcher_exec(&[OPEN_LOGS_FLAG], None)?;
+    entry.add_action(LOGS_ACTION).set("Name", "Open logs").set("Exec", &logs);
+
+    Ok((entry, icons_changed))
+}
+
+fn studio_years(entry: &DesktopEntry) -> Vec<String> {
+    entry
+        .actions()
+        .iter()
+        .filter_map(|action| action.strip_prefix(STUDIO_ACTION_PREFIX))
+        .map(String::from)
+        .collect()
+}
+
+/* Anything we cant parse counts as stale and gets rewritten */
+async fn read_desktop_entry(path: &PathBuf) -> Option<DesktopEntry> {
+    let content = fs::read_to_string(path).await.ok()?;
+    DesktopEntry::parse(&content).ok()
+}
+
+/*
+    Exec holds the binary path and its arguments so both are covered by comparing it.
+    The arguments are compared instead of the raw line so entries quoted differently still count as current.
+    MimeType is checked too since the uri handler stops working without it
+*/
+fn entry_is_current(existing_entry: &DesktopEntry, current_entry: &DesktopEntry) -> bool {
+    let (Some(existing), Some(current)) = (existing_entry.main(), current_entry.main()) else {
+        return false;
+    };
+
+    let exec = |group: &desktop_entry::Group| group.get("Exec").map(|exec| desktop_entry::split_exec(&exec));
+    let main_current =
+        exec(existing) == exec(current) &&
+        existing.get("Icon") == current.get("Icon") &&
+        existing.get_list("MimeType") == current.get_list("MimeType");
+
+    main_current && actions_are_current(existing_entry, current_entry)
+}
+
+/* Actions are written by us in full so they have to match exactly */
+fn actions_are_current(existing: &DesktopEntry, current: &DesktopEntry) -> bool {
+    let actions = current.actions();
+    existing.actions() == actions && actions.iter().all(|id| existing.action(id) == current.action(id))
+}
+
+async fn set_mime_default(desktop_file_name: &str, mime: &str) -> Result<()> {
     let mut cmd = Command::new("xdg-mime");
-    cmd.args(["default", name, &generated_uri]);
+    cmd.args(["default", desktop_file_name, mime]);
 
     cmd.spawn()?.wait()?;
 
     Ok(())
 }
 
-async fn generate_desktop<T: AsRef<str>>(
-    name: T,
-    arguments: &[&str],
-    uri: Option<&str>
-) -> Result<()> {
-    let desktop_content = generate_desktop_str(arguments)?;
+async fn generate_uri<P: AsRef<str>, U: AsRef<str>>(desktop_file_name: P, uri: U) -> Result<()> {
+    let generated_uri: String = format!("x-scheme-handler/{}", uri.as_ref());
+    set_mime_default(desktop_file_name.as_ref(), &generated_uri).await
+}
+
+const LAUNCHER_DESKTOP: &str = "syntax-desktop";
+/* Studios used to get their own entries, these are only looked for to clean them up */
+const LEGACY_STUDIO_DESKTOP_PREFIX: &str = "syntax-studio-";
+const STUDIO_ACTION_PREFIX: &str = "studio-";
+const REPAIR_ACTION: &str = "repair";
+const LOGS_ACTION: &str = "open-logs";
+
+/* Hidden entry that only exists to open place files */
+const PLACES_DESKTOP: &str = "syntax-places";
+const PLACES_MIME_PACKAGE: &str = "syntax-places.xml";
+
+/* Handled by the ui, see launch.ts */
+const REPAIR_FLAG: &str = "--repair";
+const OPEN_LOGS_FLAG: &str = "--open-logs";
+const PLAYER_MIME: &str = "x-scheme-handler/syntax-player";
+
+/* Whatever handled syntax-player before us, put back when we unregister */
+const PREVIOUS_HANDLER_FILE: &str = "previous-uri-handler";
+
+fn applications_folder() -> Result<PathBuf> {
     let Some(data_dir) = dirs::data_local_dir() else {
         return Err(CouldntGetFolder.into());
     };
+    Ok(data_dir.join("applications"))
+}
+
+/* Where desktop files can live, ours first then the system ones. XDG_DATA_DIRS has a default when unset */
+fn application_folders() -> Vec<PathBuf> {
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    applications_folder()
+        .ok()
+        .into_iter()
+        .chain(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| PathBuf::from(dir).join("applications"))
+        )
+        .collect()
+}
+
+/* Desktop file ids with a dash can also be in a subfolder, eg kde-foo.desktop as kde/foo.desktop */
+fn find_desktop_file(desktop_file_name: &str) -> Option<PathBuf> {
+    let nested = desktop_file_name.split_once('-').map(|(folder, rest)| PathBuf::from(folder).join(rest));
+    application_folders()
+        .into_iter()
+        .flat_map(|folder| {
+            let direct = folder.join(desktop_file_name);
+            let nested = nested.as_ref().map(|nested| folder.join(nested));
+            std::iter::once(direct).chain(nested)
+        })
+        .find(|path| path.is_file())
+}
+
+/* Missing files are fine, they might never have been created */
+async fn remove_if_exists(path: PathBuf) -> Result<()> {
+    match fs::remove_file(&path).await {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn mimeapps_path() -> Result<PathBuf> {
+    let Some(config_dir) = dirs::config_dir() else {
+        return Err(CouldntGetFolder.into());
+    };
+    Ok(config_dir.join("mimeapps.list"))
+}
+
+/* First handler listed for the mime type under [Default Applications] */
+async fn read_mime_default(mime: &str) -> Result<Option<String>> {
+    let mimeapps = mimeapps_path()?;
+    if !mimeapps.exists() {
+        return Ok(None);
+    }
+
+    let mut in_defaults = false;
+    for line in fs::read_to_string(mimeapps).await?.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_defaults = line == "[Default Applications]";
+            continue;
+        }
+        if !in_defaults {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == mime {
+                let handler = value.split(';').map(str::trim).find(|app| !app.is_empty());
+                return Ok(handler.map(String::from));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/* Only remembers handlers that are not ours so registering twice cant lose the original */
+async fn remember_previous_handler(desktop_file_name: &str) -> Result<()> {
+    let Some(previous) = read_mime_default(PLAYER_MIME).await? else {
+        return Ok(());
+    };
+    if previous == desktop_file_name {
+        return Ok(());
+    }
+
+    tracing::info!(previous, "remembering previous uri handler");
+    fs::write(config::config_folder()?.join(PREVIOUS_HANDLER_FILE), previous).await?;
+    Ok(())
+}
+
+async fn restore_previous_handler() -> Result<()> {
+    let file = config::config_folder()?.join(PREVIOUS_HANDLER_FILE);
+    if !file.exists() {
+        return Ok(());
+    }
+
+    let previous = fs::read_to_string(&file).await?;
+    let previous = previous.trim();
+
+    /* The other application might have been removed since, it could be installed system wide too */
+    let previous_file = if previous.is_empty() || previous.contains('/') { None } else { find_desktop_file(previous) };
+    if let Some(previous_file) = previous_file {
+        let name = read_desktop_entry(&previous_file)
+            .await
+            .and_then(|entry| entry.main()?.get_localized("Name", desktop_entry::system_locale().as_deref()));
+        tracing::info!(previous, name, "restoring previous uri handler");
+        generate_uri(previous, "syntax-player").await?;
+    }
+
+    remove_if_exists(file).await
+}
+
+/* Not every desktop ships update-desktop-database so failing to run it is not an error */
+fn refresh_desktop_database() -> Result<()> {
+    let applications = applications_folder()?;
+    match Command::new("update-desktop-database").arg(applications).status() {
+        Ok(status) if !status.success() => {
+            tracing::warn!(code = ?status.code(), "update-desktop-database failed");
+        }
+        Err(e) => tracing::warn!(error = %e, "couldnt run update-desktop-database"),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/* Drops our desktop file from every association in mimeapps.list, other entries are kept as is */
+async fn remove_mime_associations(desktop_file_name: &str) -> Result<()> {
+    let mimeapps = mimeapps_path()?;
+    if !mimeapps.exists() {
+        return Ok(());
+    }
+
+    let mut lines = vec![];
+    for line in fs::read_to_string(&mimeapps).await?.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            lines.push(line.to_string());
+            continue;
+        };
+
+        let remaining: Vec<&str> = value
+            .split(';')
+            .filter(|app| !app.is_empty() && *app != desktop_file_name)
+            .collect();
+
+        if remaining.is_empty() {
+            continue;
+        }
+        if value.ends_with(';') {
+            lines.push(format!("{}={};", key, remaining.join(";")));
+        } else {
+            lines.push(format!("{}={}", key, remaining.join(";")));
+        }
+    }
+
+    fs::write(mimeapps, lines.join("\n") + "\n").await?;
+    Ok(())
+}
+
+/*
+    Rewrites entries left pointing at an old binary after the launcher was updated or moved.
+    The uri is made ours as well when given, the entry might have been written without it before.
+    Returns true when anything was written
+*/
+async fn write_desktop<T: AsRef<str>>(name: T, entry: DesktopEntry, uri: Option<&str>) -> Result<bool> {
     let name = format!("{}.desktop", name.as_ref());
-    let desktop_file = data_dir.join("applications").join(&name);
-    if !desktop_file.exists() {
-        fs::write(desktop_file, desktop_content).await?;
+    let desktop_file = applications_folder()?.join(&name);
+
+    let mut changed = true;
+    if desktop_file.exists() {
+        let existing = read_desktop_entry(&desktop_file).await;
+        if existing.is_some_and(|existing| entry_is_current(&existing, &entry)) {
+            changed = false;
+        } else {
+            /* The mime association points at the file name so it survives the rewrite */
+            tracing::info!(entry = name, "rewriting stale desktop entry");
+            fs::write(&desktop_file, entry.to_string()).await?;
+        }
+    } else {
+        fs::write(&desktop_file, entry.to_string()).await?;
+    }
 
-        if let Some(to_reg) = uri {
+    if let Some(to_reg) = uri {
+        let mime = format!("x-scheme-handler/{}", to_reg);
+        if read_mime_default(&mime).await?.as_deref() != Some(name.as_str()) {
+            remember_previous_handler(&name).await?;
             generate_uri(name, to_reg).await?;
+            changed = true;
         }
     }
-    Ok(())
+    Ok(changed)
+}
+
+fn launcher_desktop_file() -> Result<PathBuf> {
+    Ok(applications_folder()?.join(format!("{}.desktop", LAUNCHER_DESKTOP)))
+}
+
+/*
+    None keeps the studio actions the entry already has, so registering the uri never drops them.
+    Entries written before icons were installed point at the binary and get rewritten here.
+    Only set_defaults passes the uri, a new entry written for studio actions is not made the handler
+*/
+async fn update_launcher_entry(years: Option<Vec<String>>, uri: Option<&str>) -> Result<bool> {
+    let years = match years {
+        Some(years) => years,
+        None =>
+            read_desktop_entry(&launcher_desktop_file()?).await
+                .map(|existing| studio_years(&existing))
+                .unwrap_or_default(),
+    };
+
+    let (entry, icons_changed) = generate_launcher_entry(&years).await?;
+    let entry_changed = write_desktop(LAUNCHER_DESKTOP, entry, uri).await?;
+
+    if icons_changed {
+        icons::refresh_icon_cache();
+    }
+    if entry_changed {
+        refresh_desktop_database()?;
+    }
+    Ok(icons_changed || entry_changed)
+}
+
+/* Removes the per year entries older launchers wrote, true if any were found */
+async fn remove_legacy_studio_entries() -> Result<bool> {
+    let applications = applications_folder()?;
+    if !applications.exists() {
+        return Ok(false);
+    }
+
+    let mut removed = false;
+    let mut entries = fs::read_dir(applications).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let is_studio = name
+            .to_str()
+            .is_some_and(|name| name.starts_with(LEGACY_STUDIO_DESKTOP_PREFIX) && name.ends_with(".desktop"));
+
+        if is_studio {
+            remove_if_exists(entry.path()).await?;
+            removed = true;
+        }
+    }
+
+    Ok(removed)
+}
+
+pub async fn set_defaults() -> Result<bool> {
+    update_launcher_entry(None, Some("syntax-player")).await
+}
+
+pub fn state_files() -> Result<Vec<PathBuf>> {
+    Ok(vec![config::config_folder()?.join(PREVIOUS_HANDLER_FILE)])
+}
+
+pub async fn unset_defaults() -> Result<()> {
+    let name = format!("{}.desktop", LAUNCHER_DESKTOP);
+    tracing::info!(mime = PLAYER_MIME, "removing uri handler");
+
+    remove_mime_associations(&name).await?;
+    remove_if_exists(applications_folder()?.join(name)).await?;
+    restore_previous_handler().await?;
+
+    refresh_desktop_database()
+}
+
+/* Called with the list from studio::get_available so the actions follow it */
+pub async fn create_studio_shortcuts(versions: Vec<&str>) -> Result<bool> {
+    let removed_legacy = remove_legacy_studio_entries().await?;
+    let years = versions.into_iter().map(String::from).collect();
+    let changed = update_launcher_entry(Some(years), None).await?;
+
+    if removed_legacy && !changed {
+        refresh_desktop_database()?;
+    }
+    Ok(changed || removed_legacy)
 }
 
-pub async fn set_defaults() -> Result<()> {
-    generate_desktop("syntax-desktop", &[], Some("syntax-player")).await
+pub async fn refresh_studio_shortcuts() -> Result<bool> {
+    if !launcher_desktop_file()?.exists() {
+        return Ok(false);
+    }
+    update_launcher_entry(None, None).await
 }
 
-pub async fn create_studio_shortcuts(versions: Vec<&str>) -> Result<()> {
-    for version in versions {
-        generate_desktop(format!("syntax-studio-{}", version), &["--studio", version], None).await?;
+pub async fn remove_studio_shortcut(version: &str) -> Result<()> {
+    let name = format!("{}{}.desktop", LEGACY_STUDIO_DESKTOP_PREFIX, version);
+    remove_if_exists(applications_folder()?.join(name)).await?;
+    icons::remove_studio_icon(version).await?;
+    icons::refresh_icon_cache();
+
+    /* Only drop the action, removing a studio shouldnt register the uri handler */
+    if let Some(existing) = read_desktop_entry(&launcher_desktop_file()?).await {
+        let years = studio_years(&existing)
+            .into_iter()
+            .filter(|year| year != version)
+            .collect();
+        update_launcher_entry(Some(years), None).await?;
+    }
+
+    refresh_desktop_database()
+}
+
+pub async fn remove_all_shortcuts() -> Result<()> {
+    remove_legacy_studio_entries().await?;
+    icons::remove_all_icons().await?;
+    icons::refresh_icon_cache();
+
+    /* The launcher entry stays until the uri is unregistered, it just loses its studio actions */
+    if launcher_desktop_file()?.exists() {
+        update_launcher_entry(Some(vec![]), None).await?;
+    }
+
+    refresh_desktop_database()
+}
+
+fn place_mime(extension: &str) -> String {
+    format!("application/x-roblox-{}", extension)
+}
+
+fn mime_folder() -> Result<PathBuf> {
+    let Some(data_dir) = dirs::data_local_dir() else {
+        return Err(CouldntGetFolder.into());
+    };
+    Ok(data_dir.join("mime"))
+}
+
+/* Desktops dont know the place extensions so we ship a shared-mime-info package for them */
+fn place_mime_package() -> String {
+    let mut package = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<mime-info xmlns=\"http://www.freedesktop.org/standards/shared-mime-info\">\n"
+    );
+    for extension in PLACE_EXTENSIONS {
+        package.push_str(&format!("  <mime-type type=\"{}\">\n", place_mime(extension)));
+        package.push_str(&format!("    <comment>Roblox place ({})</comment>\n", extension));
+        package.push_str(&format!("    <glob pattern=\"*.{}\"/>\n", extension));
+        package.push_str("  </mime-type>\n");
+    }
+    package.push_str("</mime-info>\n");
+    package
+}
+
+/* Missing on some systems just like update-desktop-database */
+fn refresh_mime_database() -> Result<()> {
+    let mime = mime_folder()?;
+    match Command::new("update-mime-database").arg(mime).status() {
+        Ok(status) if !status.success() => {
+            tracing::warn!(code = ?status.code(), "update-mime-database failed");
+        }
+        Err(e) => tracing::warn!(error = %e, "couldnt run update-mime-database"),
+        _ => {}
     }
 
     Ok(())
 }
+
+fn generate_places_entry() -> Result<DesktopEntry> {
+    let mimes: Vec<String> = PLACE_EXTENSIONS.iter().map(|extension| place_mime(extension)).collect();
+
+    let mut desktop = DesktopEntry::new();
+    desktop
+        .main_mut()
+        .set("Type", "Application")
+        .set("Name", "SYNTAX Studio")
+        .set("Comment", "Open places in Syntax Studio")
+        .set("Exec", &launcher_exec(&[OPEN_PLACE_FLAG], Some("%f"))?)
+        .set_bool("Terminal", false)
+        .set_bool("NoDisplay", true)
+        .set_list("MimeType", &mimes)
+        .set("Icon", icons::LAUNCHER_ICON)
+        .set_list("Categories", &["Development"]);
+
+    Ok(desktop)
+}
+
+/* Registers the place mime types and makes us their default, true when anything changed */
+pub async fn register_place_files(take_over: bool) -> Result<bool> {
+    let package = mime_folder()?.join("packages").join(PLACES_MIME_PACKAGE);
+    let package_content = place_mime_package();
+    let package_changed = fs::read_to_string(&package).await.ok().as_deref() != Some(package_content.as_str());
+    if package_changed {
+        fs::create_dir_all(package.parent().unwrap_or(&package)).await?;
+        fs::write(&package, package_content).await?;
+        refresh_mime_database()?;
+    }
+
+    let mut changed = package_changed;
+    changed |= icons::install_launcher_icon().await?;
+    changed |= write_desktop(PLACES_DESKTOP, generate_places_entry()?, None).await?;
+
+    /* A type with no default gets us, one the user pointed at another editor keeps it */
+    let name = format!("{}.desktop", PLACES_DESKTOP);
+    for extension in PLACE_EXTENSIONS {
+        let mime = place_mime(extension);
+        let current = read_mime_default(&mime).await?;
+        if current.as_deref() == Some(name.as_str()) {
+            continue;
+        }
+        if current.is_some() && !take_over {
+            tracing::info!(mime, current, "place files open with another app, leaving it");
+            continue;
+        }
+        set_mime_default(&name, &mime).await?;
+        changed = true;
+    }
+
+    if changed {
+        refresh_desktop_database()?;
+    }
+    Ok(changed)
+}
+
+pub async fn unregister_place_files() -> Result<()> {
+    let name = format!("{}.desktop", PLACES_DESKTOP);
+    remove_mime_associations(&name).await?;
+    remove_if_exists(applications_folder()?.join(name)).await?;
+    remove_if_exists(mime_folder()?.join("packages").join(PLACES_MIME_PACKAGE)).await?;
+
+    refresh_mime_database()?;
+    refresh_desktop_database()
+}
@@ -1,15 +1,58 @@
 use std::error::Error;
+use std::path::PathBuf;
 
 #[cfg_attr(windows, path = "windows.rs")]
 #[cfg_attr(target_os = "linux", path = "linux.rs")]
 mod register;
 
+#[cfg(target_os = "linux")]
+mod desktop_entry;
+#[cfg(target_os = "linux")]
+mod icons;
+
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
-pub async fn register_uri() -> Result<()> {
+/* Followed by the place file, handled by the ui in launch.ts */
+pub const OPEN_PLACE_FLAG: &str = "--open-place";
+/* Followed by the year and optionally a place file */
+pub const STUDIO_FLAG: &str = "--studio";
+
+/* Both return true when an existing registration was stale and had to be rewritten or was missing */
+pub async fn register_uri() -> Result<bool> {
     return register::set_defaults().await;
 }
 
-pub async fn create_studio_shortcuts(versions: Vec<&str>) -> Result<()> {
+pub async fn create_studio_shortcuts(versions: Vec<&str>) -> Result<bool> {
     register::create_studio_shortcuts(versions).await
 }
+
+/* Rewrites the shortcuts that exist without adding any, used after the launcher updated */
+pub async fn refresh_studio_shortcuts() -> Result<bool> {
+    register::refresh_studio_shortcuts().await
+}
+
+pub async fn unregister_uri() -> Result<()> {
+    register::unset_defaults().await
+}
+
+pub async fn remove_studio_shortcut(year: &str) -> Result<()> {
+    register::remove_studio_shortcut(year).await
+}
+
+pub async fn remove_all_shortcuts() -> Result<()> {
+    register::remove_all_shortcuts().await
+}
+
+/* Place types another app is the default for are left alone unless take_over is set, ie the user asked for it */
+pub async fn register_place_files(take_over: bool) -> Result<bool> {
+    register::register_place_files(take_over).await
+}
+
+pub async fn unregister_place_files() -> Result<()> {
+    register::unregister_place_files().await
+}
+
+/* Bookkeeping registering leaves in the config folder, for a full uninstall to remove */
+pub fn state_files() -> Result<Vec<PathBuf>> {
+    register::state_files()
+}
@@ -1,12 +1,16 @@
 use std::env;
+use std::fs;
+use std::io::ErrorKind;
 use std::error::Error;
 use std::fmt::Display;
+use std::path::{ Path, PathBuf };
 use mslnk::ShellLink;
 use winreg::RegKey;
 use winreg::enums::*;
 use mslnk;
 
-use crate::installer::paths;
+use crate::installer::{ paths, places::PLACE_EXTENSIONS };
+use super::{ OPEN_PLACE_FLAG, STUDIO_FLAG };
 
 type Result<T> = std::result::Result<T, Box<dyn Error>>;
 
@@ -21,31 +25,106 @@ impl Display for CouldntLocateExe {
 
 impl Error for CouldntLocateExe {}
 
-pub async fn create_studio_shortcuts(versions: Vec<&str>) -> Result<()> {
-    println!("Getting paths");
-    let path = paths::shortcut_path()?;
+/* Class the place extensions point at */
+const PLACE_PROG_ID: &str = "Syntax.Place";
+
+fn studio_shortcut_name(year: &str) -> String {
+    format!("Syntax Studio {}.lnk", year)
+}
+
+/* Shortcuts cant be read back so only missing ones count as a change */
+fn write_studio_shortcut(target: &str, year: &str, location: &Path) -> Result<()> {
+    let mut sl = ShellLink::new(target)?;
+    let mut arguments = paths::portable_args();
+    arguments.extend([STUDIO_FLAG, year]);
+    sl.set_arguments(Some(arguments.join(" ")));
+    sl.create_lnk(location)?;
+    Ok(())
+}
+
+fn launcher_target() -> Result<String> {
     let exe_path = env::current_exe()?;
     let Some(target) = exe_path.to_str() else {
         return Err(CouldntLocateExe.into());
     };
+    Ok(target.to_string())
+}
+
+pub async fn create_studio_shortcuts(versions: Vec<&str>) -> Result<bool> {
+    let path = paths::shortcut_path()?;
+    let target = launcher_target()?;
+    let mut changed = false;
 
     for year in versions {
-        let target = format!("{}", target);
-        let output_location = path.join(format!("Syntax Studio {}.lnk", year));
+        let output_location = path.join(studio_shortcut_name(year));
         if output_location.exists() {
             continue;
         }
-        println!("{}", output_location.display());
-        let mut sl = ShellLink::new(target)?;
-        sl.set_arguments(Some(format!("--studio {}", year)));
-        sl.create_lnk(output_location)?;
+        tracing::info!(shortcut = %output_location.display(), "creating studio shortcut");
+        write_studio_shortcut(&target, year, &output_location)?;
+        changed = true;
     }
 
-    Ok(())
+    Ok(changed)
+}
+
+/*
+    Shortcuts cant be read back so every one we made is written again.
+    Each new one is written beside the old and renamed over it, so a failure leaves the old shortcut in place
+*/
+pub async fn refresh_studio_shortcuts() -> Result<bool> {
+    let path = paths::shortcut_path()?;
+    /* Collected first so the renames below cant show up in the listing */
+    let entries = match fs::read_dir(&path).and_then(|entries| entries.collect::<std::io::Result<Vec<_>>>()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(false);
+        }
+        Err(e) => {
+            return Err(e.into());
+        }
+    };
+
+    let target = launcher_target()?;
+    let mut changed = false;
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(year) = name.strip_prefix("Syntax Studio ").and_then(|rest| rest.strip_suffix(".lnk")) else {
+            continue;
+        };
+
+        let temp = entry.path().with_extension("lnk.new");
+        if let Err(e) = write_studio_shortcut(&target, year, &temp).and_then(|_| Ok(fs::rename(&temp, entry.path())?)) {
+            let _ = fs::remove_file(&temp);
+            return Err(e);
+        }
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+/* The file or uri the shell hands us always comes last */
+fn launcher_command(exe: &str, arguments: &[&str]) -> String {
+    let mut command = format!("\"{}\"", exe);
+    for argument in paths::portable_args().iter().chain(arguments) {
+        command.push_str(&format!(" {}", argument));
+    }
+    format!("{} \"%1\"", command)
+}
+
+/* Writes the value and reports whether it was different before */
+fn replace_value(key: &RegKey, name: &str, value: &str) -> Result<bool> {
+    let existing: Option<String> = key.get_value(name).ok();
+    key.set_value(name, &value)?;
+    Ok(existing.as_deref() != Some(value))
 }
 
-/* This function is wrongly a future but thats to keep compatability with the linux function */
-pub async fn set_defaults() -> Result<()> {
+/*
+    This function is wrongly a future but thats to keep compatability with the linux function.
+    Returns true when the registered command had to change
+*/
+pub async fn set_defaults() -> Result<bool> {
     /* Modified from original src 
     /  Changes
     / [-] Uneeded function calls
@@ -68,13 +147,103 @@ pub async fn set_defaults() -> Result<()> {
         hkey_syntax_player_shell_open.create_subkey("command")?;
 
     let (defaulticon, _) = hkey_syntax_player.create_subkey("DefaultIcon")?;
-    hkey_syntax_player_shell_open_command.set_value(
-        "",
-        &format!("\"{}\" \"%1\"", current_exe_path)
-    )?;
-    defaulticon.set_value("", &format!("\"{}\",0", current_exe_path))?;
+    let command = launcher_command(current_exe_path, &[]);
+    let icon = format!("\"{}\",0", current_exe_path);
+
+    let existing_command: Option<String> = hkey_syntax_player_shell_open_command.get_value("").ok();
+    let existing_icon: Option<String> = defaulticon.get_value("").ok();
+    let changed = existing_command.as_ref() != Some(&command) || existing_icon.as_ref() != Some(&icon);
+
+    hkey_syntax_player_shell_open_command.set_value("", &command)?;
+    defaulticon.set_value("", &icon)?;
     hkey_syntax_player.set_value("", &format!("URL: Syntax Protocol"))?;
     hkey_syntax_player.set_value("URL Protocol", &"")?;
 
+    Ok(changed)
+}
+
+pub async fn remove_studio_shortcut(year: &str) -> Result<()> {
+    let shortcut = paths::shortcut_path()?.join(studio_shortcut_name(year));
+    if shortcut.exists() {
+        fs::remove_file(shortcut)?;
+    }
+
     Ok(())
 }
+
+/* Every shortcut lives in the one start menu folder so it goes as a whole */
+pub async fn remove_all_shortcuts() -> Result<()> {
+    fs::remove_dir_all(paths::shortcut_path()?)?;
+    Ok(())
+}
+
+/* Nothing is remembered outside the registry */
+pub fn state_files() -> Result<Vec<PathBuf>> {
+    Ok(vec![])
+}
+
+pub async fn unset_defaults() -> Result<()> {
+    let hkey_current_user = RegKey::predef(HKEY_CURRENT_USER);
+    let hkey_classes_root: RegKey = hkey_current_user.open_subkey("Software\\Classes")?;
+
+    match hkey_classes_root.delete_subkey_all("syntax-player") {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/* Points .rbxl and .rbxlx at the launcher, returns true when anything had to change */
+pub async fn register_place_files(take_over: bool) -> Result<bool> {
+    let exe_path = env::current_exe()?;
+    let Some(current_exe_path) = exe_path.to_str() else {
+        return Err(CouldntLocateExe.into());
+    };
+
+    let hkey_current_user = RegKey::predef(HKEY_CURRENT_USER);
+    let hkey_classes_root: RegKey = hkey_current_user.open_subkey("Software\\Classes")?;
+
+    let (prog_id, _) = hkey_classes_root.create_subkey(PLACE_PROG_ID)?;
+    let (command_key, _) = prog_id.create_subkey("shell\\open\\command")?;
+    let (icon_key, _) = prog_id.create_subkey("DefaultIcon")?;
+
+    let mut changed = replace_value(&prog_id, "", "Roblox Place")?;
+    changed |= replace_value(&command_key, "", &launcher_command(current_exe_path, &[OPEN_PLACE_FLAG]))?;
+    changed |= replace_value(&icon_key, "", &format!("\"{}\",0", current_exe_path))?;
+
+    /* The ProgID is always ours to update, the extension only when nothing else owns it */
+    for extension in PLACE_EXTENSIONS {
+        let (extension_key, _) = hkey_classes_root.create_subkey(format!(".{}", extension))?;
+        let current: Option<String> = extension_key.get_value("").ok().filter(|value: &String| !value.is_empty());
+        if current.as_deref().is_some_and(|current| current != PLACE_PROG_ID) && !take_over {
+            tracing::info!(extension, current, "place files open with another app, leaving it");
+            continue;
+        }
+        changed |= replace_value(&extension_key, "", PLACE_PROG_ID)?;
+    }
+
+    Ok(changed)
+}
+
+/* Extensions are only cleared while they still point at us */
+pub async fn unregister_place_files() -> Result<()> {
+    let hkey_current_user = RegKey::predef(HKEY_CURRENT_USER);
+    let hkey_classes_root: RegKey = hkey_current_user.open_subkey("Software\\Classes")?;
+
+    for extension in PLACE_EXTENSIONS {
+        let name = format!(".{}", extension);
+        let Ok(extension_key) = hkey_classes_root.open_subkey_with_flags(name, KEY_ALL_ACCESS) else {
+            continue;
+        };
+        let current: Option<String> = extension_key.get_value("").ok();
+        if current.as_deref() == Some(PLACE_PROG_ID) {
+            extension_key.delete_value("")?;
+        }
+    }
+
+    match hkey_classes_root.delete_subkey_all(PLACE_PROG_ID) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,200 @@
+use std::env;
+use std::fs;
+use std::path::{ Component, Path, PathBuf };
+
+use super::{ paths, Result, InvalidPath };
+use crate::config;
+
+/* Drive wine maps to the host root unless the prefix was set up differently */
+const ROOT_DRIVE: &str = "Z:";
+
+/* Configured prefix first, then the portable one. None leaves it to wine */
+pub fn configured_prefix() -> Result<Option<PathBuf>> {
+    match config::get().wine.prefix {
+        Some(prefix) => Ok(Some(prefix)),
+        None => paths::get_portable_wine_prefix(),
+    }
+}
+
+/* Same lookup wine itself does when we dont set WINEPREFIX */
+fn effective_prefix() -> Result<Option<PathBuf>> {
+    if let Some(prefix) = configured_prefix()? {
+        return Ok(Some(prefix));
+    }
+    if let Some(prefix) = env::var_os("WINEPREFIX") {
+        return Ok(Some(PathBuf::from(prefix)));
+    }
+    Ok(dirs::home_dir().map(|home| home.join(".wine")))
+}
+
+/* Removes . and .. without touching the disk so paths that dont exist yet still work */
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    if let Ok(canonical) = path.canonicalize() {
+        return Ok(canonical);
+    }
+    if path.is_absolute() {
+        return Ok(normalize(path));
+    }
+    Ok(normalize(&env::current_dir()?.join(path)))
+}
+
+/* Drive letters the prefix maps to host folders, eg c: to drive_c. Z: is left to the fallback */
+fn drive_mappings(prefix: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(prefix.join("dosdevices")) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_ascii_uppercase();
+            let is_drive = name.len() == 2 && name.ends_with(':') && name.starts_with(|c: char| c.is_ascii_alphabetic());
+            if !is_drive || name == ROOT_DRIVE {
+                return None;
+            }
+            Some((name, entry.path().canonicalize().ok()?))
+        })
+        .collect()
+}
+
+fn join_windows(drive: &str, relative: &Path) -> Result<String> {
+    let mut translated = format!("{}\\", drive);
+    let mut parts = vec![];
+    for component in relative.components() {
+        if let Component::Normal(part) = component {
+            let Some(part) = part.to_str() else {
+                return Err(InvalidPath { path: relative.to_path_buf() }.into());
+            };
+            parts.push(part);
+        }
+    }
+    translated.push_str(&parts.join("\\"));
+    Ok(translated)
+}
+
+/*
+    Translates a host path into the path a program running under wine sees.
+    The most specific drive the prefix maps wins, everything else goes through Z:
+*/
+pub fn to_wine_path_in(path: &Path, prefix: Option<&Path>) -> Result<String> {
+    let path = absolute(path)?;
+
+    let mappings = prefix.map(drive_mappings).unwrap_or_default();
+    let best = mappings
+        .iter()
+        .filter(|(_, target)| path.starts_with(target))
+        .max_by_key(|(_, target)| target.components().count());
+
+    match best {
+        Some((drive, target)) => join_windows(drive, path.strip_prefix(target)?),
+        None => join_windows(ROOT_DRIVE, &path),
+    }
+}
+
+pub fn to_wine_path(path: &Path) -> Result<String> {
+    to_wine_path_in(path, effective_prefix()?.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    /* A prefix laid out like wineboot leaves it, with d: pointing at a folder outside the prefix */
+    fn prefix() -> (TempDir, TempDir) {
+        let prefix = tempfile::tempdir().unwrap();
+        let games = tempfile::tempdir().unwrap();
+        let dosdevices = prefix.path().join("dosdevices");
+        fs::create_dir_all(prefix.path().join("drive_c").join("users")).unwrap();
+        fs::create_dir_all(&dosdevices).unwrap();
+
+        symlink("../drive_c", dosdevices.join("c:")).unwrap();
+        symlink(games.path(), dosdevices.join("d:")).unwrap();
+        symlink("/", dosdevices.join("z:")).unwrap();
+        /* Not drives, wine keeps com ports and such in here too */
+        symlink("/dev/null", dosdevices.join("com1")).unwrap();
+        (prefix, games)
+    }
+
+    #[test]
+    fn drive_c() {
+        let (prefix, _games) = prefix();
+        let path = prefix.path().join("drive_c").join("users");
+        assert_eq!(to_wine_path_in(&path, Some(prefix.path())).unwrap(), "C:\\users");
+        assert_eq!(to_wine_path_in(&prefix.path().join("drive_c"), Some(prefix.path())).unwrap(), "C:\\");
+    }
+
+    #[test]
+    fn custom_drive() {
+        let (prefix, games) = prefix();
+        let place = games.path().join("places").join("Crossroads.rbxl");
+        assert_eq!(to_wine_path_in(&place, Some(prefix.path())).unwrap(), "D:\\places\\Crossroads.rbxl");
+    }
+
+    #[test]
+    fn falls_back_to_z() {
+        let (prefix, _games) = prefix();
+        let path = Path::new("/opt/syntax/place.rbxl");
+        assert_eq!(to_wine_path_in(path, Some(prefix.path())).unwrap(), "Z:\\opt\\syntax\\place.rbxl");
+        assert_eq!(to_wine_path_in(path, None).unwrap(), "Z:\\opt\\syntax\\place.rbxl");
+    }
+
+    #[test]
+    fn missing_dosdevices_falls_back_to_z() {
+        let prefix = tempfile::tempdir().unwrap();
+        let path = Path::new("/opt/syntax");
+        assert_eq!(to_wine_path_in(path, Some(prefix.path())).unwrap(), "Z:\\opt\\syntax");
+    }
+
+    #[test]
+    fn normalizes_parent_dirs() {
+        let (prefix, _games) = prefix();
+        let path = prefix.path().join("drive_c").join("users").join("..").join("Program Files").join("x.exe");
+        assert_eq!(to_wine_path_in(&path, Some(prefix.path())).unwrap(), "C:\\Program Files\\x.exe");
+
+        let path = Path::new("/opt/./syntax/../other/file");
+        assert_eq!(to_wine_path_in(path, None).unwrap(), "Z:\\opt\\other\\file");
+    }
+
+    #[test]
+    fn root() {
+        let (prefix, _games) = prefix();
+        assert_eq!(to_wine_path_in(Path::new("/"), Some(prefix.path())).unwrap(), "Z:\\");
+    }
+
+    #[test]
+    fn spaces() {
+        let (prefix, games) = prefix();
+        let folder = games.path().join("my places");
+        fs::create_dir_all(&folder).unwrap();
+        let place = folder.join("Happy Home.rbxl");
+        fs::write(&place, b"").unwrap();
+        assert_eq!(to_wine_path_in(&place, Some(prefix.path())).unwrap(), "D:\\my places\\Happy Home.rbxl");
+        assert_eq!(to_wine_path_in(Path::new("/opt/a b/c d"), None).unwrap(), "Z:\\opt\\a b\\c d");
+    }
+
+    #[test]
+    fn most_specific_drive_wins() {
+        let (prefix, _games) = prefix();
+        let nested = prefix.path().join("drive_c").join("users").join("steam");
+        fs::create_dir_all(&nested).unwrap();
+        symlink(&nested, prefix.path().join("dosdevices").join("e:")).unwrap();
+
+        assert_eq!(to_wine_path_in(&nested.join("game"), Some(prefix.path())).unwrap(), "E:\\game");
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs::{ self, File, OpenOptions },
+    io::{ BufRead, BufReader, Read, Write },
+    net::{ Ipv4Addr, TcpListener, TcpStream },
+    path::Path,
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use fs2::FileExt;
+use rand::{ distributions::Alphanumeric, Rng };
+use serde::{ Deserialize, Serialize };
+use tauri::{ AppHandle, Manager };
+
+use crate::{ config, logging::redact_args };
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/*
+    The running launcher also writes the port it listens on next to the lock, followed by a token on the next line.
+    Both live in the config folder so they stay put when the install root moves
+*/
+pub const LOCK_FILE: &str = "launcher.lock";
+pub const PORT_FILE: &str = "launcher.port";
+
+/*
+    Any local user can connect to a localhost port, so a forward has to carry the token
+    from the port file which only our user can read
+*/
+const TOKEN_LENGTH: usize = 32;
+
+/* Forwarded arguments are small, this only stops a stray connection from filling memory */
+const MAX_FORWARD_BYTES: u64 = 64 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/* The first launcher might still be starting up when the second one tries to reach it */
+const CONNECT_ATTEMPTS: u32 = 20;
+const CONNECT_DELAY: Duration = Duration::from_millis(100);
+
+/* Sent back once a forward is queued, a closing launcher hangs up instead */
+const ACCEPTED: &str = "ok";
+/* A launcher that turned a forward away is about to exit, its lock is waited on this long */
+const CLOSING_ATTEMPTS: u32 = 50;
+
+pub const FORWARDED_EVENT: &str = "forwarded_launch";
+
+/* Forwarded launches waiting for the ui, closing is set once it has decided to exit */
+#[derive(Default)]
+struct Pending {
+    launches: VecDeque<Vec<String>>,
+    closing: bool,
+}
+
+/* Arguments the ui is working on plus any that were forwarded while it was busy */
+pub struct LaunchState {
+    current: Mutex<Vec<String>>,
+    pending: Mutex<Pending>,
+}
+
+impl LaunchState {
+    pub fn new(args: Vec<String>) -> Self {
+        LaunchState {
+            current: Mutex::new(args),
+            pending: Mutex::new(Pending::default()),
+        }
+    }
+
+    pub fn current(&self) -> Vec<String> {
+        self.current.lock().map(|args| args.clone()).unwrap_or_default()
+    }
+
+    /* False once the ui is closing, the forwarding launcher has to run the launch itself then */
+    fn push(&self, args: Vec<String>) -> bool {
+        let Ok(mut pending) = self.pending.lock() else {
+            return false;
+        };
+        if pending.closing {
+            return false;
+        }
+
+        pending.launches.push_back(args);
+        true
+    }
+
+    /*
+        Swaps in the next forwarded launch, false means there is nothing left to do.
+        With closing set an empty queue also turns away every later forward, so none arrive after the last check
+    */
+    pub fn advance(&self, closing: bool) -> bool {
+        let next = self.pending.lock().ok().and_then(|mut pending| {
+            let next = pending.launches.pop_front();
+            if next.is_none() && closing {
+                pending.closing = true;
+            }
+            next
+        });
+        let Some(next) = next else {
+            return false;
+        };
+
+        match self.current.lock() {
+            Ok(mut current) => {
+                *current = next;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+pub struct Primary {
+    /* Dropping this releases the lock so it lives until the process exits */
+    _lock: File,
+    listener: TcpListener,
+    token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Forward {
+    token: String,
+    args: Vec<String>,
+}
+
+pub enum Instance {
+    Primary(Primary),
+    Forwarded,
+}
+
+/* The primary might be between writing the lock and the port file, that gets retried like a refused connection */
+fn parse_port_file(content: &str) -> Result<(u16, String)> {
+    let mut lines = content.lines();
+    let port = lines.next().unwrap_or_default().trim().parse::<u16>()?;
+    let token = lines.next().unwrap_or_default().trim();
+    if token.is_empty() {
+        return Err("The running launcher has not written its token yet".into());
+    }
+    Ok((port, token.to_string()))
+}
+
+#[cfg(target_os = "linux")]
+fn write_port_file(path: &Path, content: &str) -> Result<()> {
+    use std::os::unix::fs::{ OpenOptionsExt, PermissionsExt };
+
+    let mut file = OpenOptions::new().create(true).truncate(true).write(true).mode(0o600).open(path)?;
+    /* mode only applies to new files, one left by an older launcher keeps its permissions otherwise */
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/* The config folder is under the users AppData which other users cant read */
+#[cfg(target_os = "windows")]
+fn write_port_file(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content)?;
+    Ok(())
+}
+
+/* False when the running launcher is closing and wont take the launch */
+fn forward(args: &[String]) -> Result<bool> {
+    let port_file = config::config_folder()?.join(PORT_FILE);
+    let mut last_error: Box<dyn Error> = "Couldnt reach the running launcher".into();
+
+    for _ in 0..CONNECT_ATTEMPTS {
+        let attempt = fs
+            ::read_to_string(&port_file)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|content| parse_port_file(&content))
+            .and_then(|(port, token)| Ok((TcpStream::connect((Ipv4Addr::LOCALHOST, port))?, token)));
+
+        match attempt {
+            Ok((mut stream, token)) => {
+                let forward = Forward { token, args: args.to_vec() };
+                writeln!(stream, "{}", serde_json::to_string(&forward)?)?;
+
+                stream.set_read_timeout(Some(READ_TIMEOUT))?;
+                let mut reply = String::new();
+                BufReader::new(stream.take(MAX_FORWARD_BYTES)).read_line(&mut reply)?;
+                return Ok(reply.trim() == ACCEPTED);
+            }
+            Err(e) => {
+                last_error = e;
+            }
+        }
+
+        thread::sleep(CONNECT_DELAY);
+    }
+
+    Err(last_error)
+}
+
+/*
+    Only one launcher may run at a time since they would race on the same install folders.
+    A second launcher hands its arguments to the first one and then exits
+*/
+pub fn acquire(args: &[String]) -> Result<Instance> {
+    let state_folder = config::config_folder()?;
+    let lock = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(state_folder.join(LOCK_FILE))?;
+
+    if lock.try_lock_exclusive().is_err() {
+        tracing::info!(args = ?redact_args(args), "launcher already running, forwarding arguments");
+        if forward(args)? {
+            return Ok(Instance::Forwarded);
+        }
+
+        tracing::info!("running launcher is closing, waiting to take over");
+        let mut attempts = 0;
+        while lock.try_lock_exclusive().is_err() {
+            attempts += 1;
+            if attempts == CLOSING_ATTEMPTS {
+                return Err("The running launcher refused the launch but never closed".into());
+            }
+            thread::sleep(CONNECT_DELAY);
+        }
+    }
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let token: String = rand::thread_rng().sample_iter(&Alphanumeric).take(TOKEN_LENGTH).map(char::from).collect();
+    write_port_file(&state_folder.join(PORT_FILE), &format!("{}\n{}\n", listener.local_addr()?.port(), token))?;
+
+    Ok(Instance::Primary(Primary { _lock: lock, listener, token }))
+}
+
+/* Compares every byte so the time taken doesnt give away how much of the token matched */
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len() &&
+        expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+fn read_forwarded(stream: &TcpStream, token: &str) -> Result<Vec<String>> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_FORWARD_BYTES)).read_line(&mut line)?;
+
+    let forward: Forward = serde_json::from_str(&line)?;
+    if !token_matches(token, &forward.token) {
+        return Err("Forwarded launch did not carry the launcher token".into());
+    }
+    Ok(forward.args)
+}
+
+impl Primary {
+    /* Queues forwarded arguments on the LaunchState managed by the app */
+    pub fn listen(self, app: AppHandle) {
+        thread::spawn(move || {
+            /* Keep the lock alive as long as we are listening */
+            let _lock = self._lock;
+
+            for stream in self.listener.incoming() {
+                let forwarded = stream
+                    .map_err(Box::<dyn Error>::from)
+                    .and_then(|stream| Ok((read_forwarded(&stream, &self.token)?, stream)));
+                let (args, mut stream) = match forwarded {
+                    Ok(forwarded) => forwarded,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "failed to read forwarded arguments");
+                        continue;
+                    }
+                };
+
+                tracing::info!(args = ?redact_args(&args), "received forwarded launch");
+                if !app.state::<LaunchState>().push(args) {
+                    tracing::info!("closing, leaving the forwarded launch to its own launcher");
+                    continue;
+                }
+                if let Err(e) = writeln!(stream, "{}", ACCEPTED) {
+                    tracing::warn!(error = %e, "failed to accept a forwarded launch");
+                }
+
+                if let Err(e) = app.emit_all(FORWARDED_EVENT, ()) {
+                    tracing::warn!(error = %e, "failed to notify the ui of a forwarded launch");
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(line: &str, token: &str) -> Result<Vec<String>> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        writeln!(client, "{}", line).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        read_forwarded(&stream, token)
+    }
+
+    #[test]
+    fn accepts_the_token() {
+        let line = serde_json::to_string(&(Forward { token: "secret".into(), args: vec!["a".into(), "b".into()] })).unwrap();
+        assert_eq!(send(&line, "secret").unwrap(), ["a", "b"]);
+    }
+
+    #[test]
+    fn rejects_a_wrong_or_missing_token() {
+        let line = serde_json::to_string(&(Forward { token: "guess".into(), args: vec![] })).unwrap();
+        assert!(send(&line, "secret").is_err());
+        assert!(send("[\"syntax-player://\"]", "secret").is_err());
+        assert!(send("{\"args\":[]}", "secret").is_err());
+    }
+
+    #[test]
+    fn closing_turns_away_later_forwards() {
+        let state = LaunchState::new(vec!["first".into()]);
+        assert!(state.push(vec!["second".into()]));
+
+        /* Idle checks leave the queue open */
+        assert!(state.advance(false));
+        assert_eq!(state.current(), ["second"]);
+        assert!(!state.advance(false));
+        assert!(state.push(vec!["third".into()]));
+
+        /* A queued launch still runs before closing takes effect */
+        assert!(state.advance(true));
+        assert_eq!(state.current(), ["third"]);
+        assert!(!state.advance(true));
+        assert!(!state.push(vec!["fourth".into()]));
+        assert!(!state.advance(false));
+    }
+
+    #[test]
+    fn port_file_needs_a_token() {
+        assert_eq!(parse_port_file("4012\nabc\n").unwrap(), (4012, "abc".to_string()));
+        assert!(parse_port_file("4012").is_err());
+        assert!(parse_port_file("4012\n\n").is_err());
+        assert!(parse_port_file("").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn port_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let folder = tempfile::tempdir().unwrap();
+        let path = folder.path().join(PORT_FILE);
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_port_file(&path, "1\ntoken\n").unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1\ntoken\n");
+    }
+}
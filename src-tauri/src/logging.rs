@@ -0,0 +1,115 @@
+use std::{ error::Error, fs::{ self, File }, io::{ self, Write }, path::{ Path, PathBuf } };
+
+use chrono::Local;
+use tracing_appender::{ non_blocking::WorkerGuard, rolling::{ RollingFileAppender, Rotation } };
+use tracing_subscriber::{ fmt, prelude::*, EnvFilter };
+use zip::{ write::FileOptions, ZipWriter };
+
+use crate::config;
+use crate::installer::{ paths, launch_uri::PLAYER_SCHEME };
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/* Overrides the configured level, takes the usual tracing directives */
+const LOG_ENV: &str = "SYNTAX_LOG";
+
+pub const REDACTED: &str = "[redacted]";
+
+/* Launch arguments whose following value is a secret */
+const SECRET_FLAGS: [&str; 2] = ["--authenticationTicket", "--joinScriptUrl"];
+
+/* Parts of a syntax-player:// uri whose value is a secret */
+const SECRET_URI_KEYS: [&str; 2] = ["gameinfo", "placelauncherurl"];
+
+/*
+    The guard flushes the file writer when dropped so it has to live as long as main.
+    Stderr is kept as well for debug builds since release builds have no console
+*/
+pub fn init() -> Result<WorkerGuard> {
+    let config = config::get();
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("launcher")
+        .filename_suffix("log")
+        .max_log_files(config.retention.launcher_logs)
+        .build(paths::get_logs_folder()?)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter = EnvFilter::try_from_env(LOG_ENV).unwrap_or_else(|_|
+        EnvFilter::new(&config.log_level)
+    );
+
+    tracing_subscriber
+        ::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .with(fmt::layer().with_writer(io::stderr))
+        .try_init()?;
+    config::report_load_issues();
+
+    Ok(guard)
+}
+
+pub fn redact_uri(uri: &str) -> String {
+    uri.split('+')
+        .map(|part| {
+            match part.split_once(':') {
+                Some((key, _)) if SECRET_URI_KEYS.contains(&key) => format!("{}:{}", key, REDACTED),
+                _ => part.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("+")
+}
+
+/* Used before logging any argument list that might end up holding an auth ticket */
+pub fn redact_args<S: AsRef<str>>(args: &[S]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut hide_next = false;
+
+    for arg in args {
+        let arg = arg.as_ref();
+        if hide_next {
+            redacted.push(REDACTED.to_string());
+        } else if arg.starts_with(PLAYER_SCHEME) {
+            redacted.push(redact_uri(arg));
+        } else {
+            redacted.push(arg.to_string());
+        }
+        hide_next = SECRET_FLAGS.contains(&arg);
+    }
+
+    redacted
+}
+
+fn add_folder(zip: &mut ZipWriter<File>, folder: &Path, prefix: &str) -> Result<()> {
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let name = format!("{}{}", prefix, name);
+
+        if path.is_dir() {
+            add_folder(zip, &path, &format!("{}/", name))?;
+            continue;
+        }
+
+        zip.start_file(name, FileOptions::default())?;
+        zip.write_all(&fs::read(&path)?)?;
+    }
+
+    Ok(())
+}
+
+/* Zips the launcher and launch logs together so they can be attached to a bug report */
+pub fn bundle_logs<P: AsRef<Path>>(destination: P) -> Result<PathBuf> {
+    let name = format!("syntax-logs-{}.zip", Local::now().format("%Y%m%d-%H%M%S"));
+    let bundle = destination.as_ref().join(name);
+
+    let mut zip = ZipWriter::new(File::create(&bundle)?);
+    add_folder(&mut zip, &paths::get_logs_folder()?, "")?;
+    zip.finish()?;
+
+    Ok(bundle)
+}
@@ -1,11 +1,14 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{ fmt::Display, error::Error };
+use std::{ fmt::Display, error::Error, env };
 use tauri::Manager;
 
 mod commands;
 mod installer;
+mod logging;
+mod instance;
+mod config;
 
 #[derive(Debug)]
 pub struct FailedInit;
@@ -19,27 +22,97 @@ impl Display for FailedInit {
 impl Error for FailedInit {}
 
 fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    installer::paths::init_portable(&mut args);
+
+    /* Keep launching even if the log folder is unusable */
+    let _log_guard = match logging::init() {
+        Ok(guard) => Some(guard),
+        Err(e) => {
+            eprintln!("Failed to start logging: {}", e);
+            None
+        }
+    };
+
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        portable = installer::paths::portable_root().is_some(),
+        args = ?logging::redact_args(&args),
+        "launcher started"
+    );
+
+    installer::places::resolve_place_args(&mut args);
+    let primary = match instance::acquire(&args) {
+        Ok(instance::Instance::Primary(primary)) => Some(primary),
+        Ok(instance::Instance::Forwarded) => {
+            return;
+        }
+        /* Better to risk a second launcher than to drop the launch */
+        Err(e) => {
+            tracing::warn!(error = %e, "single instance check failed");
+            None
+        }
+    };
+
     tauri::Builder
         ::default()
+        .manage(instance::LaunchState::new(args))
         .invoke_handler(
             tauri::generate_handler![
                 commands::get_available_studio,
                 commands::studio_installed,
                 commands::install_studio,
+                commands::studio_outdated,
                 commands::get_valid_clients,
                 commands::get_client_manifest,
                 commands::download_zip,
+                commands::check_download_space,
+                commands::check_extract_space,
                 commands::extract_zip,
                 commands::client_installed,
+                commands::prune_client_versions,
                 commands::prepare_client,
                 commands::get_client_folder,
                 commands::get_latest_version,
                 commands::get_bootstrapper_info,
+                commands::check_launcher_update,
+                commands::install_launcher_update,
+                commands::finish_launcher_update,
                 commands::get_cli,
+                commands::get_player_launch,
+                commands::next_launch,
+                commands::lock_client_install,
+                commands::unlock_client_install,
                 commands::create_uri,
+                commands::unregister_uri,
+                commands::create_place_association,
                 commands::create_shortcuts,
                 commands::launch_studio,
-                commands::launch_client
+                commands::resolve_place_year,
+                commands::install_studio_plugins,
+                commands::update_studio_plugins,
+                commands::remove_studio_plugin,
+                commands::list_studio_plugins,
+                commands::resolve_client_flags,
+                commands::get_client_flags,
+                commands::set_client_flags,
+                commands::set_flag_preset,
+                commands::list_content_packs,
+                commands::apply_content_packs,
+                commands::revert_content_packs,
+                commands::open_content_packs_folder,
+                commands::launch_client,
+                commands::open_launch_log,
+                commands::export_launch_log,
+                commands::bundle_logs,
+                commands::uninstall_client,
+                commands::uninstall_studio,
+                commands::uninstall_all,
+                commands::open_logs_folder,
+                commands::find_broken_installs,
+                commands::get_config,
+                commands::set_config,
+                commands::migrate_install
             ]
         )
         .setup(|app| {
@@ -52,6 +125,10 @@ fn main() {
             window.center()?;
             window.set_always_on_top(true)?;
 
+            if let Some(primary) = primary {
+                primary.listen(app.handle());
+            }
+
             Ok(())
         })
         .run(tauri::generate_context!())
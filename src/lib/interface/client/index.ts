@@ -4,36 +4,32 @@ import {
   GetClientFolder,
   GetManifest,
   SetTaskbar,
+  ApplyContentPacks,
   clientInstalled,
   download_zip,
   extract_zip,
   prepare_client,
 } from "./utility";
-import { GetBootstrapperInfo, type LaunchArguments } from "..";
-import { exit } from "@tauri-apps/api/process";
+import { CRASH_WINDOW, CheckReport, type LaunchReport } from "../report";
+import { Finish, Idle } from "../launch";
+import { GetConfig } from "../config";
 
 export * from "./utility";
 
-export async function LaunchClient(year: string, version: string, launch_args: LaunchArguments) {
-  let base_url = (await GetBootstrapperInfo()).base_url;
-  await invoke("launch_client", {
+/* The launcher reads the auth ticket from its own arguments */
+export async function LaunchClient(year: string, version: string) {
+  let report: LaunchReport = await invoke("launch_client", {
     year,
     version,
-    args: [
-      "--play",
-      "--authenticationUrl",
-      `https://${base_url}/Login/Negotiate.ashx`,
-      "--authenticationTicket",
-      launch_args.auth_ticket,
-      "--joinScriptUrl",
-      launch_args.join_script,
-    ],
+    crashWindow: CRASH_WINDOW,
   });
+  CheckReport(`Client ${year}`, report);
 
   await SetTaskbar("Client launched", 100);
-  setTimeout(async () => {
-    await exit(0);
-  }, 3000);
+
+  let config = await GetConfig();
+  if (config.close_after_launch) Finish(config.close_delay_ms);
+  else Idle();
 }
 
 export default class Installer {
@@ -59,6 +55,12 @@ export default class Installer {
     await prepare_client(this.Year, this.Version, this.Manifest);
   }
 
+  private async CheckSpace() {
+    if (!this.Manifest) throw "Manifest undefined";
+    let fileNames = Object.keys(this.Manifest).map((key) => `${this.Version}-${key}`);
+    await invoke("check_download_space", { fileNames });
+  }
+
   private async DownloadFiles(start_number = 10) {
     if (!this.Manifest) throw "Manifest undefined";
 
@@ -74,6 +76,8 @@ export default class Installer {
     if (!this.Manifest) throw "Manifest undefined";
 
     let client_folder = await GetClientFolder(this.Year, this.Version);
+    let fileNames = Object.keys(this.Manifest).map((key) => `${this.Version}-${key}`);
+    await invoke("check_extract_space", { fileNames, location: client_folder });
     let extraction = [];
 
     for (let [filename, location] of Object.entries(this.Manifest)) {
@@ -91,23 +95,39 @@ export default class Installer {
       return;
     }
 
-    this.Taskbar("Downloading client manifest", 0);
+    /* Stops another launch from writing into the same version folder */
+    await invoke("lock_client_install", { year: this.Year, version: this.Version });
+    try {
+      this.Taskbar("Downloading client manifest", 0);
+
+      await this.DownloadManifest();
 
-    await this.DownloadManifest();
+      this.Taskbar("Manifest downloaded. Preparing for download", 5);
 
-    this.Taskbar("Manifest downloaded. Preparing for download", 5);
+      this.Taskbar("Checking disk space", 5);
 
-    await this.PrepareDownload();
+      await this.CheckSpace();
 
-    this.Taskbar("Preparations finished downloading files", 10);
+      await this.PrepareDownload();
 
-    await this.DownloadFiles();
+      this.Taskbar("Preparations finished downloading files", 10);
 
-    this.Taskbar("Downloads finished, extracing files", 60);
+      await this.DownloadFiles();
 
-    await this.ExtractFiles();
+      this.Taskbar("Downloads finished, extracing files", 60);
+
+      await this.ExtractFiles();
+
+      this.Taskbar("Applying content packs", 95);
+
+      await ApplyContentPacks(this.Year, this.Version);
+
+      this.Taskbar("Download finished", 100);
+    } finally {
+      await invoke("unlock_client_install", { year: this.Year, version: this.Version });
+    }
 
-    this.Taskbar("Download finished", 100);
+    await invoke("prune_client_versions", { year: this.Year, version: this.Version });
   }
 
   constructor(year: string, version: string, verbose: boolean = true) {
@@ -69,8 +69,40 @@ export async function clientInstalled(year: string, version: string): Promise<bo
   return await invoke("client_installed", { year, version });
 }
 
+/**
+ * Leaving out the version removes every version of the year
+ */
+export async function UninstallClient(year: string, version?: string) {
+  await invoke("uninstall_client", { year, version });
+}
+
 export async function SetTaskbar(...args: Array<string | number>) {
   for (let arg of args) {
     await emit("set_taskbar", arg);
   }
 }
+
+/**
+ * Folders in the content packs folder, in the order they are applied
+ */
+export async function ListContentPacks(): Promise<string[]> {
+  return await invoke("list_content_packs");
+}
+
+/**
+ * Returns true when the content folder changed
+ */
+export async function ApplyContentPacks(year: string, version: string): Promise<boolean> {
+  return await invoke("apply_content_packs", { year, version });
+}
+
+/**
+ * Puts back every file the packs replaced
+ */
+export async function RevertContentPacks(year: string, version: string) {
+  await invoke("revert_content_packs", { year, version });
+}
+
+export async function OpenContentPacksFolder() {
+  await invoke("open_content_packs_folder");
+}
@@ -0,0 +1,89 @@
+import { invoke } from "@tauri-apps/api";
+
+export type ServerProfile = {
+  base_url: string;
+  setup_url: string;
+};
+
+export type Flags = { [flag: string]: boolean | number | string };
+
+/**
+ * Presets are applied in order, flags win over all of them
+ */
+export type ClientFlags = {
+  presets: string[];
+  flags: Flags;
+};
+
+export type Config = {
+  install_root: string | null;
+  metadata: { repo: string; branch: string };
+  server_profile: string;
+  servers: { [name: string]: ServerProfile };
+  concurrency: number;
+  bandwidth_limit: number | null;
+  retention: {
+    client_versions: number;
+    studio_versions: number;
+    launch_logs: number;
+    launcher_logs: number;
+  };
+  wine: { binary: string; args: string[]; prefix: string | null };
+  default_studio_year: string | null;
+  flag_presets: { [name: string]: Flags };
+  client_flags: { [year: string]: ClientFlags };
+  log_level: "trace" | "debug" | "info" | "warn" | "error";
+  auto_update: boolean;
+  close_after_launch: boolean;
+  close_delay_ms: number;
+};
+
+let configCache: Config | undefined;
+
+export async function GetConfig(): Promise<Config> {
+  if (configCache === undefined) {
+    configCache = await invoke("get_config");
+    return GetConfig();
+  }
+  return configCache;
+}
+
+/**
+ * The launcher validates the config and rejects it with a readable error
+ */
+export async function SetConfig(config: Config) {
+  await invoke("set_config", { config });
+  configCache = config;
+}
+
+/**
+ * Moves every client, studio and download to a new folder and points the config at it
+ */
+export async function MigrateInstall(newRoot: string) {
+  await invoke("migrate_install", { newRoot });
+  configCache = undefined;
+}
+
+/**
+ * The flags the next launch of that year writes to ClientAppSettings.json
+ */
+export async function ResolveClientFlags(year: string): Promise<Flags> {
+  return await invoke("resolve_client_flags", { year });
+}
+
+export async function GetClientFlags(year: string): Promise<ClientFlags> {
+  return await invoke("get_client_flags", { year });
+}
+
+export async function SetClientFlags(year: string, client: ClientFlags) {
+  await invoke("set_client_flags", { year, client });
+  configCache = undefined;
+}
+
+/**
+ * Leaving out flags deletes the preset
+ */
+export async function SetFlagPreset(name: string, flags?: Flags) {
+  await invoke("set_flag_preset", { name, flags });
+  configCache = undefined;
+}
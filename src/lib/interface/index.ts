@@ -3,6 +3,9 @@ import { invoke } from "@tauri-apps/api";
 export * from "./studio";
 export * from "./client";
 export * from "./launch";
+export * from "./report";
+export * from "./config";
+export * from "./update";
 
 type BootstrapperInfo = {
   base_url: String;
@@ -17,10 +20,34 @@ export async function GetBootstrapperInfo(): Promise<BootstrapperInfo> {
   return cache!;
 }
 
-export async function CreateShortcuts(studioVersions: string[]) {
-  await invoke("create_shortcuts", { studioVersions });
+/** Resolves true when a missing or stale shortcut had to be written */
+export async function CreateShortcuts(studioVersions: string[]): Promise<boolean> {
+  return await invoke("create_shortcuts", { studioVersions });
 }
 
-export async function CreateUri() {
-  await invoke("create_uri");
+/**
+ * Resolves true when .rbxl and .rbxlx had to be pointed back at the launcher.
+ * Types another app opens are only taken over when the user asked for it
+ */
+export async function CreatePlaceAssociation(takeOver = false): Promise<boolean> {
+  return await invoke("create_place_association", { takeOver });
+}
+
+/** Resolves true when the uri handler pointed somewhere else and was rewritten */
+export async function CreateUri(): Promise<boolean> {
+  return await invoke("create_uri");
+}
+
+/**
+ * Puts back whatever handled syntax-player links before the launcher
+ */
+export async function UnregisterUri() {
+  await invoke("unregister_uri");
+}
+
+/**
+ * Removes every install, shortcut and the uri handler
+ */
+export async function UninstallAll() {
+  await invoke("uninstall_all");
 }
@@ -1,8 +1,29 @@
 import { invoke } from "@tauri-apps/api";
-import { GetStudios, InstallStudio, LaunchStudio, StudioInstalled } from "./studio";
-import { CreateShortcuts, CreateUri, GetLatestversion, GetValidClients, clientInstalled } from ".";
+import { GetStudios, InstallStudio, LaunchStudio, StudioInstalled, StudioOutdated } from "./studio";
+import {
+  CreatePlaceAssociation,
+  CreateShortcuts,
+  CreateUri,
+  GetLatestversion,
+  GetValidClients,
+  clientInstalled,
+} from ".";
 import Installer, { LaunchClient, SetTaskbar } from "./client";
 import { exit } from "@tauri-apps/api/process";
+import { listen } from "@tauri-apps/api/event";
+import { OpenLogsFolder, RepairInstalls } from "./repair";
+import { FinishLauncherUpdate, PendingLauncherUpdate, UpdateLauncher } from "./update";
+
+/* Passed by the desktop actions on linux */
+const REPAIR_FLAG = "--repair";
+const OPEN_LOGS_FLAG = "--open-logs";
+
+/* --studio <year> [file] picks the year, --open-place <file> comes from the file manager */
+const STUDIO_FLAG = "--studio";
+const OPEN_PLACE_FLAG = "--open-place";
+
+/* Emitted by the launcher when another one hands its arguments over */
+const FORWARDED_EVENT = "forwarded_launch";
 let args_cache: string[] | undefined;
 async function GetLaunchArguments(): Promise<string[]> {
   if (args_cache === undefined) {
@@ -16,7 +37,13 @@ export async function IsStudio() {
   if (args.length < 3) {
     return false;
   }
-  return args[1] === "--studio";
+  return args[1] === STUDIO_FLAG || args[1] === OPEN_PLACE_FLAG;
+}
+
+/** The place file studio should open, if any */
+async function GetPlaceFile(): Promise<string | undefined> {
+  let args = await GetLaunchArguments();
+  return args[1] === OPEN_PLACE_FLAG ? args[2] : args[3];
 }
 
 async function GetStudioVersions(): Promise<string[]> {
@@ -25,17 +52,19 @@ async function GetStudioVersions(): Promise<string[]> {
 
 async function GetStudioLaunch(): Promise<string> {
   let launch_args = await GetLaunchArguments();
-  let launched_version = launch_args[2];
+  let launched_version: string =
+    launch_args[1] === OPEN_PLACE_FLAG
+      ? await invoke("resolve_place_year", { file: launch_args[2] })
+      : launch_args[2];
 
   if (!(await GetStudioVersions()).includes(launched_version))
     throw `${launched_version} dose not have a studio`;
   return launched_version;
 }
 
+/* The auth ticket and join script stay in the launcher, only these reach the ui */
 export type LaunchArguments = {
   launch_mode: string;
-  auth_ticket: string;
-  join_script: string;
   client_year: string;
 };
 
@@ -43,61 +72,15 @@ let cachedParsed: LaunchArguments | undefined;
 
 export async function GetPlayerLaunchArguments(): Promise<LaunchArguments> {
   if (cachedParsed !== undefined) return cachedParsed;
-  let uri = (await GetLaunchArguments())[1];
-  if (!uri || !uri.startsWith("syntax-player://")) throw "No player launch arguments";
-
-  let uriArguments = uri.split("+");
-
-  let launch_mode: string | undefined;
-  let auth_ticket: string | undefined;
-  let join_script: string | undefined;
-  let client_year: string | undefined;
 
   let validClients = GetValidClients();
+  let parsed: LaunchArguments = await invoke("get_player_launch");
 
-  for (let arg of uriArguments) {
-    let index = arg.indexOf(":");
-    let first = arg.substring(0, index),
-      last = arg.substring(index + 1);
-
-    switch (first) {
-      case "launchmode": {
-        launch_mode = last;
-        continue;
-      }
-      case "gameinfo": {
-        auth_ticket = last;
-        continue;
-      }
-      case "placelauncherurl": {
-        join_script = last;
-        continue;
-      }
-      case "clientyear": {
-        client_year = last;
-        continue;
-      }
-      default:
-        continue;
-    }
-  }
-
-  if (launch_mode === undefined) {
-    throw "Launchmode undefined";
-  } else if (auth_ticket === undefined) {
-    throw "Authticket undefined";
-  } else if (join_script === undefined) {
-    throw "Joinscript undefined";
-  } else if (client_year === undefined || !(await validClients).includes(client_year)) {
+  if (!(await validClients).includes(parsed.client_year)) {
     throw "Client year undefined or invalid";
   }
 
-  cachedParsed = {
-    launch_mode,
-    auth_ticket,
-    join_script,
-    client_year,
-  };
+  cachedParsed = parsed;
   return GetPlayerLaunchArguments();
 }
 
@@ -108,14 +91,44 @@ export async function GetLaunchedVersion(): Promise<string> {
   return (await GetPlayerLaunchArguments()).client_year;
 }
 
+/**
+ * Entry point of the ui, forwarded launches go straight to HandleLaunch
+ */
+export async function StartLauncher() {
+  /* Forwards that arrive while a launch is running are queued and picked up by Finish or Idle */
+  listen(FORWARDED_EVENT, () => {
+    if (!idle) return;
+    idle = false;
+    Idle();
+  });
+  await FinishLauncherUpdate();
+  try {
+    return await HandleLaunch();
+  } finally {
+    /* Checked once the launch is underway so a slow update server never delays it */
+    UpdateLauncher();
+  }
+}
+
 export async function HandleLaunch() {
+  let flag = (await GetLaunchArguments())[1];
+  if (flag === REPAIR_FLAG) return RepairInstalls();
+  if (flag === OPEN_LOGS_FLAG) return OpenLogsFolder();
+
   try {
     let launched_version = await GetLaunchedVersion();
     if (await IsStudio()) {
       SetTaskbar(`Studio ${launched_version} launched`, 0);
       if (await StudioInstalled(launched_version)) {
+        if (await StudioOutdated(launched_version)) {
+          SetTaskbar(`Updating studio ${launched_version}`, 0);
+          /* The old build is still there so a failed update shouldnt stop the launch */
+          await InstallStudio(launched_version).catch((err) =>
+            SetTaskbar(`Studio update failed: ${err}`, 0)
+          );
+        }
         SetTaskbar(`Studio ${launched_version} installed`, 100);
-        await LaunchStudio(launched_version);
+        await LaunchStudio(launched_version, await GetPlaceFile());
         return;
       }
       await InstallStudio(launched_version);
@@ -124,7 +137,7 @@ export async function HandleLaunch() {
       let latest_version = await GetLatestversion();
       if (await clientInstalled(launched_version, latest_version)) {
         SetTaskbar(`Client ${launched_version} installed`, 0);
-        await LaunchClient(launched_version, latest_version, await GetPlayerLaunchArguments());
+        await LaunchClient(launched_version, latest_version);
         return;
       }
       let installer = new Installer(launched_version, latest_version, true);
@@ -135,11 +148,43 @@ export async function HandleLaunch() {
     if (String(err) !== `No player launch arguments`) throw err;
     SetTaskbar(`Registering uri`, 0);
     await CreateUri();
+    SetTaskbar(`Registering place files`, 25);
+    await CreatePlaceAssociation();
     SetTaskbar(`Creating shortcuts`, 50);
     await CreateShortcuts(await GetStudioVersions());
     SetTaskbar(`Done closing...`, 100);
-    setTimeout(async () => {
-      await exit(0);
-    }, 2000);
+    Finish(2000);
   }
 }
+
+/* True once the window was left open with nothing left to launch */
+let idle = false;
+
+/* Closing tells the launcher to turn away forwards from now on, they launch on their own instead */
+async function RunForwarded(closing: boolean): Promise<boolean> {
+  if (!(await invoke("next_launch", { closing }))) return false;
+  args_cache = undefined;
+  cachedParsed = undefined;
+  await HandleLaunch().catch((err) => SetTaskbar(String(err), 100));
+  return true;
+}
+
+/**
+ * Used instead of Finish when the window stays open, runs the queued launches and then waits for more
+ */
+export async function Idle() {
+  if (!(await RunForwarded(false))) idle = true;
+}
+
+/**
+ * Runs any launch another launcher forwarded to us, otherwise exits after the delay.
+ * The last check comes after the update so nothing forwarded while it downloads is lost
+ */
+export function Finish(delay: number) {
+  setTimeout(async () => {
+    if (await RunForwarded(false)) return;
+    await PendingLauncherUpdate();
+    if (await RunForwarded(true)) return;
+    await exit(0);
+  }, delay);
+}
@@ -0,0 +1,47 @@
+import { invoke } from "@tauri-apps/api";
+import { CreateShortcuts, CreateUri, GetLatestversion } from ".";
+import Installer, { SetTaskbar, UninstallClient } from "./client";
+import { GetStudios, InstallStudio } from "./studio";
+import { Finish } from "./launch";
+
+type BrokenInstalls = {
+  clients: { year: string; version: string }[];
+  studios: string[];
+};
+
+/**
+ * Re-registers the uri and shortcuts then reinstalls anything a failed install left behind.
+ * Broken old client versions are removed instead since the launcher only runs the latest
+ */
+export async function RepairInstalls() {
+  SetTaskbar("Registering uri", 0);
+  await CreateUri();
+  SetTaskbar("Creating shortcuts", 10);
+  await CreateShortcuts(Object.keys(await GetStudios()));
+
+  SetTaskbar("Checking installs", 20);
+  let broken: BrokenInstalls = await invoke("find_broken_installs");
+  let latest_version = await GetLatestversion();
+
+  for (let year of broken.studios) {
+    SetTaskbar(`Repairing studio ${year}`, 30);
+    await InstallStudio(year);
+  }
+
+  for (let { year, version } of broken.clients) {
+    if (version !== latest_version) {
+      await UninstallClient(year, version);
+      continue;
+    }
+    SetTaskbar(`Repairing client ${year}`, 60);
+    await new Installer(year, version, true).Download();
+  }
+
+  SetTaskbar("Repair finished", 100);
+  Finish(2000);
+}
+
+export async function OpenLogsFolder() {
+  await invoke("open_logs_folder");
+  Finish(0);
+}
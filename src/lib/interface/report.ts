@@ -0,0 +1,48 @@
+import { invoke } from "@tauri-apps/api";
+
+/* Seconds a launched application has to stay alive before we stop watching it */
+export const CRASH_WINDOW = 10;
+
+export type LaunchReport = {
+  running: boolean;
+  exit_code: number | null;
+  crashed: boolean;
+  uptime_ms: number;
+};
+
+export class LaunchCrashed {
+  public Name: string;
+  public Report: LaunchReport;
+
+  constructor(name: string, report: LaunchReport) {
+    this.Name = name;
+    this.Report = report;
+  }
+
+  public toString() {
+    let code = this.Report.exit_code ?? "unknown";
+    return `${this.Name} crashed after ${this.Report.uptime_ms}ms with exit code ${code}`;
+  }
+}
+
+export function CheckReport(name: string, report: LaunchReport) {
+  if (report.crashed) throw new LaunchCrashed(name, report);
+}
+
+export async function OpenLaunchLog() {
+  await invoke("open_launch_log");
+}
+
+/**
+ * Copies the latest launch log into the users downloads folder
+ */
+export async function ExportLaunchLog(): Promise<string> {
+  return await invoke("export_launch_log", {});
+}
+
+/**
+ * Zips every launcher and launch log into the users downloads folder for bug reports
+ */
+export async function BundleLogs(): Promise<string> {
+  return await invoke("bundle_logs", {});
+}
@@ -1,13 +1,28 @@
 import { invoke } from "@tauri-apps/api";
 import { z } from "zod";
 import { SetTaskbar } from ".";
-import { exit } from "@tauri-apps/api/process";
+import { Finish, Idle } from "./launch";
+import { GetConfig } from "./config";
+import { CRASH_WINDOW, CheckReport, type LaunchReport } from "./report";
 
-const StudiosValidator = z.record(z.string().length(4), z.string().url());
+/* The launcher fills in version and display_name for entries that leave them out */
+const StudioInfoValidator = z.object({
+  url: z.string().url(),
+  version: z.string(),
+  display_name: z.string(),
+  size: z.number().nullable(),
+  sha256: z.string().nullable(),
+  executable: z.string().nullable(),
+  mirrors: z.array(z.string().url()),
+  min_launcher_version: z.string().nullable(),
+});
+const StudiosValidator = z.record(z.string().length(4), StudioInfoValidator);
 
-let StudioCache: { [key: string]: string } | undefined;
+export type StudioInfo = z.infer<typeof StudioInfoValidator>;
 
-export async function GetStudios(): Promise<{ [key: string]: string }> {
+let StudioCache: { [key: string]: StudioInfo } | undefined;
+
+export async function GetStudios(): Promise<{ [key: string]: StudioInfo }> {
   if (StudioCache === undefined) {
     StudioCache = await invoke("get_available_studio").then((data) =>
       StudiosValidator.parseAsync(data)
@@ -17,32 +32,82 @@ export async function GetStudios(): Promise<{ [key: string]: string }> {
   return StudioCache;
 }
 
+/**
+ * Installs or updates the studio, the installed build keeps working until the new one is verified
+ */
 export async function InstallStudio(year: string) {
-  if (await StudioInstalled(year)) return;
+  if ((await StudioInstalled(year)) && !(await StudioOutdated(year))) return;
 
-  let url = (await GetStudios())[year];
-  if (url === undefined) throw "Bad version";
+  let info = (await GetStudios())[year];
+  if (info === undefined) throw "Bad version";
 
   /* I have no way to actually monitor this */
-  await SetTaskbar("Installing studio", 10);
-  setTimeout(async () => SetTaskbar("Installing studio", 50), 1000);
+  await SetTaskbar(`Installing ${info.display_name}`, 10);
+  setTimeout(async () => SetTaskbar(`Installing ${info.display_name}`, 50), 1000);
 
-  await invoke("install_studio", { year, url });
+  await invoke("install_studio", { year });
 }
 
 export async function StudioInstalled(year: string): Promise<boolean> {
   return await invoke("studio_installed", { year });
 }
 
+export async function StudioOutdated(year: string): Promise<boolean> {
+  return await invoke("studio_outdated", { year });
+}
+
 /**
  *
- * WARNING THIS WILL TERMINATE THE PROCESS
+ * WARNING THIS WILL TERMINATE THE PROCESS UNLESS ANOTHER LAUNCH WAS FORWARDED
  */
-export async function LaunchStudio(year: string) {
-  await invoke("launch_studio", { year });
+export async function LaunchStudio(year: string, file?: string) {
+  let report: LaunchReport = await invoke("launch_studio", {
+    year,
+    file,
+    crashWindow: CRASH_WINDOW,
+  });
+  CheckReport(`Studio ${year}`, report);
 
   await SetTaskbar("Studio launched", 100);
-  setTimeout(async () => {
-    await exit(0);
-  }, 3000);
+
+  let config = await GetConfig();
+  if (config.close_after_launch) Finish(config.close_delay_ms);
+  else Idle();
+}
+
+/**
+ * Removes the studio files and its shortcut
+ */
+export async function UninstallStudio(year: string) {
+  await invoke("uninstall_studio", { year });
+}
+
+const PluginValidator = z.object({
+  name: z.string(),
+  source: z.string(),
+  sha256: z.string(),
+});
+
+export type Plugin = z.infer<typeof PluginValidator>;
+
+/**
+ * Source can be a url, a plugin file or a folder of plugins, returns the names installed
+ */
+export async function InstallPlugins(year: string, source: string): Promise<string[]> {
+  return await invoke("install_studio_plugins", { year, source });
+}
+
+/**
+ * Fetches every plugin again from its source, returns the names that changed
+ */
+export async function UpdatePlugins(year: string): Promise<string[]> {
+  return await invoke("update_studio_plugins", { year });
+}
+
+export async function RemovePlugin(year: string, name: string) {
+  await invoke("remove_studio_plugin", { year, name });
+}
+
+export async function ListPlugins(year: string): Promise<Plugin[]> {
+  return z.array(PluginValidator).parseAsync(await invoke("list_studio_plugins", { year }));
 }
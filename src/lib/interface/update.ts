@@ -0,0 +1,51 @@
+import { invoke } from "@tauri-apps/api";
+import { SetTaskbar } from "./client";
+import { GetConfig } from "./config";
+
+export type LauncherUpdate = {
+  version: string;
+  notes: string;
+};
+
+export async function CheckLauncherUpdate(): Promise<LauncherUpdate | null> {
+  return await invoke("check_launcher_update");
+}
+
+/**
+ * Resolves the version installed, the new launcher is used from the next launch
+ */
+export async function InstallLauncherUpdate(): Promise<string | null> {
+  return await invoke("install_launcher_update");
+}
+
+/* Resolves once a background update is done, Finish waits for it so exiting never cuts one short */
+let pending_update: Promise<void> = Promise.resolve();
+
+/**
+ * Finishes an update the previous launcher installed, quick enough to run before the launch
+ */
+export async function FinishLauncherUpdate() {
+  await invoke("finish_launcher_update").catch((err) =>
+    SetTaskbar(`Couldnt finish the launcher update: ${err}`, 0)
+  );
+}
+
+/**
+ * Installs any newer launcher in the background, nothing here is allowed to stop the launch.
+ * The launcher times out the feed and download so this cant hold up exiting forever
+ */
+export function UpdateLauncher() {
+  pending_update = (async () => {
+    if (!(await GetConfig()).auto_update) return;
+    try {
+      let version = await InstallLauncherUpdate();
+      if (version !== null) SetTaskbar(`Launcher ${version} installed`, 100);
+    } catch (err) {
+      SetTaskbar(`Launcher update failed: ${err}`, 100);
+    }
+  })();
+}
+
+export function PendingLauncherUpdate(): Promise<void> {
+  return pending_update;
+}